@@ -0,0 +1,205 @@
+//! `#[derive(JsonPull)]`, implementing `jsonpull::Pull` for structs and
+//! enums. See the `jsonpull::pull` module for the JSON each shape is read
+//! from.
+//!
+//! Fields and variants accept `#[jsonpull(rename = "key")]` to read them
+//! from a different key or name, and fields `#[jsonpull(default)]` to use
+//! `Default::default()` when their key is missing.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro2::TokenStream;
+use syn::{Data, DeriveInput, Fields, FieldsNamed, Ident, LitStr};
+
+#[proc_macro_derive(JsonPull, attributes(jsonpull))]
+pub fn derive_json_pull(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream> {
+    let name = input.ident.clone();
+    let body = match input.data {
+        Data::Struct(ref s) => {
+            match s.fields {
+                Fields::Named(ref fields) => pull_object(quote!(#name), fields)?,
+                Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => {
+                    quote!(::std::result::Result::Ok(#name(::jsonpull::Pull::pull(parser)?)))
+                }
+                Fields::Unit => quote!(parser.read_null().map(|_| #name)),
+                _ => {
+                    return Err(syn::Error::new_spanned(&s.fields,
+                                                       "only newtype tuple structs are supported"))
+                }
+            }
+        }
+        Data::Enum(ref e) => pull_enum(&name, e)?,
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(&input.ident, "unions are not supported"))
+        }
+    };
+
+    let params: Vec<Ident> = input.generics.type_params().map(|p| p.ident.clone()).collect();
+    {
+        let where_clause = input.generics.make_where_clause();
+        for param in params {
+            where_clause.predicates.push(syn::parse_quote!(#param: ::jsonpull::Pull));
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::jsonpull::Pull for #name #ty_generics #where_clause {
+            fn pull<__B: ::std::io::BufRead>(parser: &mut ::jsonpull::Parser<__B>)
+                -> ::jsonpull::Result<Self>
+            {
+                #body
+            }
+        }
+    })
+}
+
+/// Options from `#[jsonpull(...)]` attributes.
+struct Attrs {
+    rename: Option<String>,
+    default: bool,
+}
+
+fn attrs(attrs: &[syn::Attribute]) -> syn::Result<Attrs> {
+    let mut result = Attrs {
+        rename: None,
+        default: false,
+    };
+    for attr in attrs {
+        if !attr.path().is_ident("jsonpull") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let s: LitStr = meta.value()?.parse()?;
+                result.rename = Some(s.value());
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                result.default = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename` or `default`"))
+            }
+        })?;
+    }
+    Ok(result)
+}
+
+/// Reads an object into the struct or struct variant at `path`.
+fn pull_object(path: TokenStream, fields: &FieldsNamed) -> syn::Result<TokenStream> {
+    let mut vars = Vec::new();
+    let mut arms = Vec::new();
+    let mut inits = Vec::new();
+    for (i, field) in fields.named.iter().enumerate() {
+        let attrs = attrs(&field.attrs)?;
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let var = format_ident!("__field{}", i);
+        let key = attrs.rename.unwrap_or_else(|| unraw(ident));
+        vars.push(quote!(let mut #var: ::std::option::Option<#ty> = ::std::option::Option::None;));
+        arms.push(quote! {
+            #key => #var = ::std::option::Option::Some(::jsonpull::Pull::pull(parser)?),
+        });
+        let missing = if attrs.default {
+            quote!(::std::default::Default::default())
+        } else {
+            quote!(<#ty as ::jsonpull::Pull>::missing(#key)?)
+        };
+        inits.push(quote! {
+            #ident: match #var {
+                ::std::option::Option::Some(v) => v,
+                ::std::option::Option::None => #missing,
+            },
+        });
+    }
+    Ok(quote! {{
+        #(#vars)*
+        parser.expect_object_start()?;
+        while let ::std::option::Option::Some(key) = parser.next_key()? {
+            match key.as_str() {
+                #(#arms)*
+                _ => parser.skip_value()?,
+            }
+        }
+        ::std::result::Result::Ok(#path { #(#inits)* })
+    }})
+}
+
+fn pull_enum(name: &Ident, data: &syn::DataEnum) -> syn::Result<TokenStream> {
+    let mut unit_arms = Vec::new();
+    let mut object_arms = Vec::new();
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let key = attrs(&variant.attrs)?.rename.unwrap_or_else(|| unraw(ident));
+        match variant.fields {
+            Fields::Unit => unit_arms.push(quote!(#key => ::std::result::Result::Ok(#name::#ident),)),
+            Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => {
+                object_arms.push(quote!(#key => #name::#ident(::jsonpull::Pull::pull(parser)?),))
+            }
+            Fields::Named(ref fields) => {
+                let body = pull_object(quote!(#name::#ident), fields)?;
+                object_arms.push(quote!(#key => { let r: ::jsonpull::Result<Self> = #body; r? }))
+            }
+            Fields::Unnamed(_) => {
+                return Err(syn::Error::new_spanned(variant,
+                                                   "only newtype tuple variants are supported"))
+            }
+        }
+    }
+    let expected = LitStr::new(&format!("variant of {}", name), name.span());
+    Ok(quote! {
+        match parser.peek_kind()? {
+            ::std::option::Option::Some(::jsonpull::Kind::String) => {
+                let variant = parser.read_string()?;
+                match variant.as_str() {
+                    #(#unit_arms)*
+                    _ => ::std::result::Result::Err(
+                        ::std::format!("unknown {} `{}`", #expected, variant).into()),
+                }
+            }
+            ::std::option::Option::Some(::jsonpull::Kind::Object) => {
+                parser.expect_object_start()?;
+                let variant = match parser.next_key()? {
+                    ::std::option::Option::Some(key) => key,
+                    ::std::option::Option::None => {
+                        return ::std::result::Result::Err(
+                            ::std::format!("expected {}, found empty object", #expected).into());
+                    }
+                };
+                let value = match variant.as_str() {
+                    #(#object_arms)*
+                    _ => {
+                        return ::std::result::Result::Err(
+                            ::std::format!("unknown {} `{}`", #expected, variant).into());
+                    }
+                };
+                parser.expect_object_end()?;
+                ::std::result::Result::Ok(value)
+            }
+            _ => {
+                ::std::result::Result::Err(
+                    ::std::format!("expected {} as string or object", #expected).into())
+            }
+        }
+    })
+}
+
+/// The name of an identifier without the `r#` of a raw identifier.
+fn unraw(ident: &Ident) -> String {
+    let s = ident.to_string();
+    match s.strip_prefix("r#") {
+        Some(s) => s.to_string(),
+        None => s,
+    }
+}
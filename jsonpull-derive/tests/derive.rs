@@ -0,0 +1,86 @@
+extern crate jsonpull;
+#[macro_use]
+extern crate jsonpull_derive;
+
+use jsonpull::{Parser, Pull};
+
+#[derive(Debug, PartialEq, JsonPull)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Debug, PartialEq, JsonPull)]
+struct Id(u64);
+
+#[derive(Debug, PartialEq, JsonPull)]
+enum Shape {
+    Empty,
+    #[jsonpull(rename = "circle")]
+    Circle { center: Point, radius: f64 },
+    Polygon(Vec<Point>),
+}
+
+#[derive(Debug, PartialEq, JsonPull)]
+struct Drawing {
+    id: Id,
+    #[jsonpull(rename = "label")]
+    name: Option<String>,
+    #[jsonpull(default)]
+    tags: Vec<String>,
+    shapes: Vec<Shape>,
+    r#type: String,
+}
+
+#[derive(Debug, PartialEq, JsonPull)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+fn pull<T: Pull>(json: &str) -> jsonpull::Result<T> {
+    T::pull(&mut Parser::from_reader(json.as_bytes()))
+}
+
+#[test]
+fn test_derive_struct() {
+    let json = r#"{
+        "id": 7,
+        "unknown": {"skipped": [1, 2, {"deep": null}]},
+        "type": "sketch",
+        "shapes": [
+            "Empty",
+            {"circle": {"radius": 1.5, "center": {"x": 1, "y": -2}}},
+            {"Polygon": [{"x": 0, "y": 0}, {"y": 1, "x": 1}]}
+        ]
+    }"#;
+    assert_eq!(pull::<Drawing>(json).unwrap(),
+               Drawing {
+                   id: Id(7),
+                   name: None,
+                   tags: vec![],
+                   shapes: vec![Shape::Empty,
+                                Shape::Circle {
+                                    center: Point { x: 1, y: -2 },
+                                    radius: 1.5,
+                                },
+                                Shape::Polygon(vec![Point { x: 0, y: 0 }, Point { x: 1, y: 1 }])],
+                   r#type: "sketch".into(),
+               });
+
+    let w: Wrapper<Option<String>> = pull(r#"{"inner": "x"}"#).unwrap();
+    assert_eq!(w.inner, Some("x".into()));
+    let w: Wrapper<Option<String>> = pull(r#"{"inner": null}"#).unwrap();
+    assert_eq!(w.inner, None);
+}
+
+#[test]
+fn test_derive_errors() {
+    let e = pull::<Point>(r#"{"x": 1}"#).unwrap_err();
+    assert_eq!(e.to_string(), "missing field `y`");
+    assert!(pull::<Point>(r#"{"x": 1, "y": "2"}"#).is_err());
+    assert!(pull::<Point>(r#"{"x": 3000000000, "y": 0}"#).is_err());
+    let e = pull::<Shape>(r#""Square""#).unwrap_err();
+    assert_eq!(e.to_string(), "unknown variant of Shape `Square`");
+    assert!(pull::<Shape>(r#"{"Polygon": [], "Empty": null}"#).is_err());
+    assert!(pull::<Shape>("1").is_err());
+}
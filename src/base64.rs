@@ -0,0 +1,125 @@
+//! Decoding base64 string values, as used for binary data embedded in JSON.
+//!
+//! Both the standard and the URL-safe alphabet of
+//! [RFC 4648](https://tools.ietf.org/html/rfc4648) are accepted, with or
+//! without padding. [`DecodeWriter`] decodes text as it is written to it, so
+//! [`Parser::read_base64_to`](::Parser::read_base64_to) never holds more than
+//! a chunk of the string in memory.
+
+use std::io::{self, Write};
+
+use Result;
+
+/// Decodes base64 text written to it and writes the bytes to `inner`.
+#[derive(Debug)]
+pub struct DecodeWriter<W: Write> {
+    inner: W,
+    /// Values of the characters of the current group of four.
+    group: [u8; 4],
+    len: usize,
+    padding: usize,
+    written: u64,
+}
+
+impl<W: Write> DecodeWriter<W> {
+    pub fn new(inner: W) -> DecodeWriter<W> {
+        DecodeWriter {
+            inner,
+            group: [0; 4],
+            len: 0,
+            padding: 0,
+            written: 0,
+        }
+    }
+
+    /// Number of decoded bytes written to `inner` so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Decodes the last, possibly incomplete, group. Fails if the text ended
+    /// in the middle of a byte or its padding.
+    pub fn finish(&mut self) -> io::Result<()> {
+        let out = match (self.len, self.padding) {
+            (0, 0) => 0,
+            (2, 0) | (2, 2) => 1,
+            (3, 0) | (3, 1) => 2,
+            _ => return Err(invalid("truncated base64")),
+        };
+        let bytes = self.decode_group();
+        self.inner.write_all(&bytes[..out])?;
+        self.written += out as u64;
+        self.len = 0;
+        self.padding = 0;
+        Ok(())
+    }
+
+    fn decode_group(&mut self) -> [u8; 3] {
+        let g = self.group;
+        self.group = [0; 4];
+        [g[0] << 2 | g[1] >> 4, g[1] << 4 | g[2] >> 2, g[2] << 6 | g[3]]
+    }
+}
+
+impl<W: Write> Write for DecodeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut out = Vec::with_capacity(buf.len() / 4 * 3 + 3);
+        for &c in buf {
+            if c == b'=' {
+                if self.len < 2 || self.len + self.padding == 4 {
+                    return Err(invalid("misplaced base64 padding"));
+                }
+                self.padding += 1;
+                continue;
+            }
+            if self.padding > 0 {
+                return Err(invalid("base64 data after padding"));
+            }
+            self.group[self.len] = match value(c) {
+                Some(v) => v,
+                None => return Err(invalid("invalid base64 character")),
+            };
+            self.len += 1;
+            if self.len == 4 {
+                out.extend_from_slice(&self.decode_group());
+                self.len = 0;
+            }
+        }
+        self.inner.write_all(&out)?;
+        self.written += out.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Value of a character of either alphabet.
+fn value(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Decodes the contents of a string value, such as that of an
+/// `Event::String`.
+pub fn decode(s: &str) -> Result<Vec<u8>> {
+    let mut w = DecodeWriter::new(Vec::with_capacity(s.len() / 4 * 3));
+    w.write_all(s.as_bytes())?;
+    w.finish()?;
+    Ok(w.into_inner())
+}
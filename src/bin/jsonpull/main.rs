@@ -0,0 +1,297 @@
+//! Command-line access to the streaming parser.
+//!
+//! Every command reads one file, or standard input when it is missing or
+//! `-`, and works in constant memory apart from the longest key.
+
+extern crate jsonpull;
+
+mod paths;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+
+use jsonpull::csv::{CsvWriter, Format};
+use jsonpull::path::{self, PathTracker, Segment, Shape, Step};
+use jsonpull::writer::JsonWriter;
+use jsonpull::{Block, DuplicateKeys, EventRef, Parser, Result};
+
+const USAGE: &str = "\
+usage: jsonpull <command> [options] [file]
+
+commands:
+  validate            check that the input is well-formed JSON
+  fmt [--indent N]    pretty-print, indenting by N spaces (default 2)
+  minify              remove all insignificant whitespace
+  get <pointer>       print the value at a JSON Pointer such as /items/0/id
+  stats               count the values in the input
+  paths               print one `path = value;` line per value
+  unpaths             rebuild JSON from the output of paths
+  csv                 convert an array or sequence of objects to CSV
+      --tsv               write TSV instead
+      --columns A,B,...   the columns to write, by default those of the
+                          first records
+      --infer N           take the columns from the first N records (default 1)
+      --no-header         leave out the row of column names
+
+options:
+  -m, --multiple      accept a sequence of values, such as NDJSON
+  -W, --warnings      report what was tolerated, such as trailing commas or
+                      duplicate keys
+  -h, --help          print this message";
+
+/// Strings longer than this are streamed in chunks.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+struct Args {
+    command: String,
+    /// Positional arguments after the command.
+    operands: Vec<String>,
+    indent: usize,
+    multiple: bool,
+    warnings: bool,
+    tsv: bool,
+    columns: Option<Vec<String>>,
+    infer: usize,
+    header: bool,
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("jsonpull: {}\n\n{}", msg, USAGE);
+    process::exit(2);
+}
+
+fn parse_args() -> Args {
+    let mut args = env::args().skip(1);
+    let mut parsed = Args {
+        command: String::new(),
+        operands: Vec::new(),
+        indent: 2,
+        multiple: false,
+        warnings: false,
+        tsv: false,
+        columns: None,
+        infer: 1,
+        header: true,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-m" | "--multiple" => parsed.multiple = true,
+            "-W" | "--warnings" => parsed.warnings = true,
+            "--indent" => parsed.indent = number(args.next(), "--indent"),
+            "--tsv" => parsed.tsv = true,
+            "--columns" => {
+                parsed.columns = match args.next() {
+                    Some(list) => Some(list.split(',').map(|c| c.to_string()).collect()),
+                    None => usage_error("--columns needs a list of columns"),
+                }
+            }
+            "--infer" => parsed.infer = number(args.next(), "--infer"),
+            "--no-header" => parsed.header = false,
+            "-" => parsed.operands.push(arg),
+            _ if arg.starts_with('-') => usage_error(&format!("unknown option {}", arg)),
+            _ if parsed.command.is_empty() => parsed.command = arg,
+            _ => parsed.operands.push(arg),
+        }
+    }
+    if parsed.command.is_empty() {
+        usage_error("missing command");
+    }
+    parsed
+}
+
+fn number(arg: Option<String>, option: &str) -> usize {
+    match arg.map(|n| n.parse()) {
+        Some(Ok(n)) => n,
+        _ => usage_error(&format!("{} needs a number", option)),
+    }
+}
+
+/// The operands a command takes before the optional input file.
+fn split_operands(args: &Args, count: usize) -> (&[String], Option<&str>) {
+    if args.operands.len() < count {
+        usage_error(&format!("{} needs {} argument(s)", args.command, count));
+    }
+    if args.operands.len() > count + 1 {
+        usage_error("too many arguments");
+    }
+    let (operands, rest) = args.operands.split_at(count);
+    (operands, rest.first().map(|s| s.as_str()))
+}
+
+fn open(file: Option<&str>) -> io::Result<(String, Box<dyn BufRead>)> {
+    Ok(match file {
+        None | Some("-") => ("<stdin>".into(), Box::new(BufReader::new(io::stdin()))),
+        Some(path) => (path.into(), Box::new(BufReader::new(File::open(path)?))),
+    })
+}
+
+fn main() {
+    let args = parse_args();
+    let file = match args.command.as_str() {
+        "get" => split_operands(&args, 1).1,
+        "validate" | "fmt" | "minify" | "stats" | "paths" | "unpaths" | "csv" => {
+            split_operands(&args, 0).1
+        }
+        cmd => usage_error(&format!("unknown command {}", cmd)),
+    };
+    let (name, reader) = match open(file) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("jsonpull: {}: {}", file.unwrap_or("-"), e);
+            process::exit(1);
+        }
+    };
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    if args.command == "unpaths" {
+        let mut w = JsonWriter::new(&mut out).indent(args.indent);
+        let r = paths::unpaths(reader, &mut w).and_then(|_| {
+            let out = w.into_inner();
+            out.write_all(b"\n")?;
+            out.flush()?;
+            Ok(())
+        });
+        if let Err(e) = r {
+            eprintln!("jsonpull: {}: {}", name, e);
+            process::exit(1);
+        }
+        return;
+    }
+    let mut parser = Parser::from_reader(reader)
+        .multiple_values(args.multiple || args.command == "csv")
+        .string_chunk_size(CHUNK_SIZE);
+    if args.warnings {
+        let name = name.clone();
+        parser = parser.duplicate_keys(DuplicateKeys::Warn)
+            .on_warning(move |w| eprintln!("jsonpull: {}: warning: {}", name, w));
+    }
+    let r = match args.command.as_str() {
+        "validate" => validate(&mut parser).map(|_| true),
+        "fmt" => write(&mut parser, JsonWriter::new(&mut out).indent(args.indent)),
+        "minify" => write(&mut parser, JsonWriter::new(&mut out)),
+        "get" => get(&mut parser, &args.operands[0], &mut out),
+        "stats" => stats(&mut parser, &mut out),
+        "paths" => paths::paths(&mut parser, &mut out),
+        "csv" => csv(&mut parser, &args, &mut out),
+        _ => unreachable!(),
+    };
+    let r = r.and_then(|found| {
+        out.flush()?;
+        Ok(found)
+    });
+    match r {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            let pos = parser.position();
+            eprintln!("jsonpull: {}:{}: offset {}: {}", name, pos.line, pos.offset, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn validate<B: BufRead>(p: &mut Parser<B>) -> Result<()> {
+    while p.next_ref()?.is_some() {}
+    Ok(())
+}
+
+fn write<B: BufRead, W: Write>(p: &mut Parser<B>, mut w: JsonWriter<W>) -> Result<bool> {
+    let mut any = false;
+    for ev in p.by_ref() {
+        w.write_event(&ev?)?;
+        any = true;
+    }
+    if any {
+        w.into_inner().write_all(b"\n")?;
+    }
+    Ok(true)
+}
+
+/// Whether `tracker` is at the value with the given reference tokens.
+fn at_pointer(tracker: &PathTracker, tokens: &[String]) -> bool {
+    tracker.depth() == tokens.len() &&
+    tracker.segments().zip(tokens).all(|(seg, token)| {
+        match *seg {
+            Segment::Key(ref k) => k == token,
+            Segment::Index(i) => i.to_string() == *token,
+        }
+    })
+}
+
+/// Prints the value at `pointer`, returning whether there was one.
+fn get<B: BufRead, W: Write>(p: &mut Parser<B>, pointer: &str, out: &mut W) -> Result<bool> {
+    let tokens = path::parse_pointer(pointer)?;
+    let mut tracker = PathTracker::new();
+    while let Some(ev) = p.next() {
+        let ev = ev?;
+        match tracker.event(&ev) {
+            Step::Scalar | Step::Open if at_pointer(&tracker, &tokens) => (),
+            _ => continue,
+        }
+        p.copy_value_to(ev, &mut JsonWriter::new(&mut *out))?;
+        out.write_all(b"\n")?;
+        return Ok(true);
+    }
+    eprintln!("jsonpull: no value at {}", pointer);
+    Ok(false)
+}
+
+fn csv<B: BufRead, W: Write>(p: &mut Parser<B>, args: &Args, out: &mut W) -> Result<bool> {
+    let mut w = CsvWriter::new(out)
+        .format(if args.tsv { Format::Tsv } else { Format::Csv })
+        .infer_columns(args.infer)
+        .header(args.header);
+    if let Some(ref columns) = args.columns {
+        w = w.columns(columns.clone());
+    }
+    w.write_records(p)?;
+    Ok(true)
+}
+
+#[derive(Default)]
+struct Stats {
+    objects: u64,
+    arrays: u64,
+    keys: u64,
+    strings: u64,
+    numbers: u64,
+    bools: u64,
+    nulls: u64,
+    max_depth: usize,
+}
+
+fn stats<B: BufRead, W: Write>(p: &mut Parser<B>, out: &mut W) -> Result<bool> {
+    let mut s = Stats::default();
+    let mut shape = Shape::new();
+    while let Some(ev) = p.next_ref()? {
+        let step = shape.step_ref(&ev);
+        match ev {
+            EventRef::Start(Block::Object) => s.objects += 1,
+            EventRef::Start(Block::Array) => s.arrays += 1,
+            EventRef::Key(_) | EventRef::RawKey(_) => s.keys += 1,
+            EventRef::String(_) | EventRef::Bytes(_) => s.strings += 1,
+            EventRef::StringChunk(_) if step == Step::Open => s.strings += 1,
+            EventRef::Number(_) => s.numbers += 1,
+            EventRef::Bool(_) => s.bools += 1,
+            EventRef::Null => s.nulls += 1,
+            _ => (),
+        }
+        s.max_depth = s.max_depth.max(p.depth());
+    }
+    writeln!(out, "bytes: {}", Parser::position(p).offset)?;
+    writeln!(out, "objects: {}", s.objects)?;
+    writeln!(out, "arrays: {}", s.arrays)?;
+    writeln!(out, "keys: {}", s.keys)?;
+    writeln!(out, "strings: {}", s.strings)?;
+    writeln!(out, "numbers: {}", s.numbers)?;
+    writeln!(out, "booleans: {}", s.bools)?;
+    writeln!(out, "nulls: {}", s.nulls)?;
+    writeln!(out, "max depth: {}", s.max_depth)?;
+    Ok(true)
+}
@@ -0,0 +1,280 @@
+//! The `paths` and `unpaths` commands: a document as one assignment per
+//! line, in the style of `gron`.
+//!
+//! ```text
+//! json = {};
+//! json.name = "jsonpull";
+//! json.authors = [];
+//! json.authors[0] = {};
+//! json.authors[0]["e-mail"] = null;
+//! ```
+//!
+//! Containers are written as `{}` or `[]` before their members, so the
+//! lines of a document can be rebuilt one at a time by `unpaths` as long as
+//! they stay in document order. Lines removed by `grep` are fine: missing
+//! containers are opened as needed and missing array elements become `null`.
+
+use std::io::{BufRead, Write};
+
+use jsonpull::path::{PathTracker, Segment, Step};
+use jsonpull::writer::{self, JsonWriter};
+use jsonpull::{Block, Event, Parser, Result};
+
+/// Name of the root value in paths.
+const ROOT: &str = "json";
+
+/// Writes one line per value of the document read by `p`.
+pub fn paths<B: BufRead, W: Write>(p: &mut Parser<B>, out: &mut W) -> Result<bool> {
+    let mut tracker = PathTracker::new();
+    for ev in p.by_ref() {
+        let ev = ev?;
+        match (tracker.event(&ev), &ev) {
+            (Step::Scalar, _) | (Step::Open, _) => (),
+            (Step::Inner, Event::StringChunk(s)) => {
+                writer::write_escaped(out, s)?;
+                continue;
+            }
+            (Step::Close, Event::StringEnd) => {
+                out.write_all(b"\";\n")?;
+                continue;
+            }
+            _ => continue,
+        }
+        write_path(out, tracker.segments())?;
+        out.write_all(b" = ")?;
+        match ev {
+            Event::Start(Block::Object) => out.write_all(b"{}")?,
+            Event::Start(Block::Array) => out.write_all(b"[]")?,
+            Event::StringChunk(ref s) => {
+                out.write_all(b"\"")?;
+                writer::write_escaped(out, s)?;
+                continue;
+            }
+            ref ev => JsonWriter::new(&mut *out).write_event(ev)?,
+        }
+        out.write_all(b";\n")?;
+    }
+    Ok(true)
+}
+
+fn write_path<'a, W, I>(out: &mut W, segments: I) -> Result<()>
+    where W: Write,
+          I: Iterator<Item = &'a Segment>
+{
+    out.write_all(ROOT.as_bytes())?;
+    for seg in segments {
+        match *seg {
+            Segment::Key(ref k) if is_identifier(k) => write!(out, ".{}", k)?,
+            Segment::Key(ref k) => {
+                out.write_all(b"[\"")?;
+                writer::write_escaped(out, k)?;
+                out.write_all(b"\"]")?;
+            }
+            Segment::Index(i) => write!(out, "[{}]", i)?,
+        }
+    }
+    Ok(())
+}
+
+/// Whether `key` can follow a `.` in a path.
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => (),
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// A container being rebuilt by `unpaths`.
+struct Frame {
+    block: Block,
+    /// The member or element most recently written.
+    current: Option<Segment>,
+    next_index: usize,
+}
+
+/// Rebuilds JSON from the lines written by `paths`.
+pub fn unpaths<B: BufRead, W: Write>(input: B, out: &mut JsonWriter<W>) -> Result<bool> {
+    let mut frames: Vec<Frame> = Vec::new();
+    for (n, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (segments, value) = parse_line(&line)
+            .map_err(|e| format!("line {}: {}", n + 1, e))?;
+        assign(&mut frames, &segments, value, out)
+            .map_err(|e| format!("line {}: {}", n + 1, e))?;
+    }
+    close(&mut frames, 0, out)?;
+    Ok(true)
+}
+
+/// Writes the events for `path = value`, closing and opening containers on
+/// the way.
+fn assign<W: Write>(frames: &mut Vec<Frame>,
+                    path: &[Segment],
+                    value: &str,
+                    out: &mut JsonWriter<W>)
+                    -> Result<()> {
+    let (last, parents) = match path.split_last() {
+        Some(split) => split,
+        None => {
+            // A new top-level value.
+            close(frames, 0, out)?;
+            return write_value(frames, value, out);
+        }
+    };
+    // Keep the frames on the way to the value's container, the root first.
+    let mut keep = if frames.is_empty() { 0 } else { 1 };
+    while keep < frames.len() && keep <= parents.len() &&
+          frames[keep - 1].current.as_ref() == Some(&parents[keep - 1]) {
+        keep += 1;
+    }
+    close(frames, keep, out)?;
+    if frames.is_empty() {
+        out.write_event(&Event::Start(block_for(&path[0])))?;
+        frames.push(Frame::new(block_for(&path[0])));
+    }
+    for (i, seg) in parents.iter().enumerate().skip(frames.len() - 1) {
+        member(frames, seg, out)?;
+        let block = block_for(&path[i + 1]);
+        out.write_event(&Event::Start(block))?;
+        frames.push(Frame::new(block));
+    }
+    member(frames, last, out)?;
+    write_value(frames, value, out)
+}
+
+impl Frame {
+    fn new(block: Block) -> Frame {
+        Frame {
+            block,
+            current: None,
+            next_index: 0,
+        }
+    }
+}
+
+/// The kind of container `seg` is a member or element of.
+fn block_for(seg: &Segment) -> Block {
+    match *seg {
+        Segment::Key(_) => Block::Object,
+        Segment::Index(_) => Block::Array,
+    }
+}
+
+/// Moves the innermost container on to `seg`.
+fn member<W: Write>(frames: &mut [Frame], seg: &Segment, out: &mut JsonWriter<W>) -> Result<()> {
+    let frame = frames.last_mut().unwrap();
+    if frame.current.as_ref() == Some(seg) {
+        return Err("value assigned twice".into());
+    }
+    match (seg, frame.block) {
+        (Segment::Key(k), Block::Object) => out.write_event(&Event::Key(k.clone()))?,
+        (&Segment::Index(i), Block::Array) => {
+            if i < frame.next_index {
+                return Err(format!("index {} out of order", i).into());
+            }
+            for _ in frame.next_index..i {
+                out.write_event(&Event::Null)?;
+            }
+            frame.next_index = i + 1;
+        }
+        (&Segment::Key(_), Block::Array) => return Err("key in an array".into()),
+        (&Segment::Index(_), Block::Object) => return Err("index in an object".into()),
+    }
+    frame.current = Some(seg.clone());
+    Ok(())
+}
+
+/// Closes containers until only `keep` are left.
+fn close<W: Write>(frames: &mut Vec<Frame>, keep: usize, out: &mut JsonWriter<W>) -> Result<()> {
+    while frames.len() > keep {
+        let frame = frames.pop().unwrap();
+        out.write_event(&Event::End(frame.block))?;
+    }
+    Ok(())
+}
+
+/// Writes the JSON text `value`, leaving `{}` and `[]` open for the lines
+/// that follow.
+fn write_value<W: Write>(frames: &mut Vec<Frame>,
+                         value: &str,
+                         out: &mut JsonWriter<W>)
+                         -> Result<()> {
+    let block = match value {
+        "{}" => Block::Object,
+        "[]" => Block::Array,
+        _ => {
+            let mut any = false;
+            for ev in Parser::from_reader(value.as_bytes()) {
+                out.write_event(&ev?)?;
+                any = true;
+            }
+            if !any {
+                return Err("missing value".into());
+            }
+            return Ok(());
+        }
+    };
+    out.write_event(&Event::Start(block))?;
+    frames.push(Frame::new(block));
+    Ok(())
+}
+
+/// Splits `json.a[0] = value;` into the path and the value text.
+fn parse_line(line: &str) -> Result<(Vec<Segment>, &str)> {
+    let line = line.trim();
+    let mut rest = match line.strip_prefix(ROOT) {
+        Some(rest) => rest,
+        None => return Err(format!("expected a path starting with `{}`", ROOT).into()),
+    };
+    let mut segments = Vec::new();
+    loop {
+        if let Some(r) = rest.strip_prefix('.') {
+            let end = r.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(r.len());
+            if end == 0 {
+                return Err("expected a key after `.`".into());
+            }
+            segments.push(Segment::Key(r[..end].to_string()));
+            rest = &r[end..];
+        } else if let Some(r) = rest.strip_prefix("[\"") {
+            let end = string_end(r).ok_or("unterminated key")?;
+            // The string, quotes included.
+            let quoted = &rest[1..end + 3];
+            let key = Parser::from_reader(quoted.as_bytes()).read_string()?;
+            segments.push(Segment::Key(key));
+            rest = r[end + 1..].strip_prefix(']').ok_or("expected `]`")?;
+        } else if let Some(r) = rest.strip_prefix('[') {
+            let end = r.find(']').ok_or("expected `]`")?;
+            let index = r[..end].parse().map_err(|_| format!("invalid index `{}`", &r[..end]))?;
+            segments.push(Segment::Index(index));
+            rest = &r[end + 1..];
+        } else {
+            break;
+        }
+    }
+    let value = rest.trim_start()
+        .strip_prefix('=')
+        .ok_or("expected `=`")?
+        .trim();
+    let value = value.strip_suffix(';').unwrap_or(value).trim_end();
+    Ok((segments, value))
+}
+
+/// The index of the quote ending the JSON string that `s` starts inside of.
+fn string_end(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
@@ -0,0 +1,272 @@
+//! Converting a stream of JSON records into CSV or TSV.
+//!
+//! The input is a top-level array of objects, or a sequence of objects such
+//! as NDJSON (see [`Parser::multiple_values`]). Nested objects are flattened
+//! into dotted column names, so `{"a": {"b": 1}}` has the column `a.b`;
+//! arrays are written as JSON text in a single cell and `null` as an empty
+//! cell.
+//!
+//! Records are converted one at a time. When the columns are not given they
+//! are taken from the first records, which are kept in memory until the
+//! header is written; by default only the first record is used. Keys not
+//! among the columns are dropped.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::mem;
+
+use writer::JsonWriter;
+use {Block, Event, Parser, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Comma-separated values as in RFC 4180: cells containing a comma,
+    /// quote or line break are quoted.
+    Csv,
+    /// Tab-separated values: tabs, line breaks and backslashes in cells are
+    /// escaped as `\t`, `\n`, `\r` and `\\`.
+    Tsv,
+}
+
+/// A record flattened into column names and cells, in the order of its keys.
+type Record = Vec<(String, String)>;
+
+/// Writes JSON records as rows, see the [module documentation](index.html).
+#[derive(Debug)]
+pub struct CsvWriter<W: Write> {
+    w: W,
+    format: Format,
+    columns: Option<Vec<String>>,
+    infer: usize,
+    header: bool,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(w: W) -> CsvWriter<W> {
+        CsvWriter {
+            w,
+            format: Format::Csv,
+            columns: None,
+            infer: 1,
+            header: true,
+        }
+    }
+
+    pub fn format(mut self, format: Format) -> CsvWriter<W> {
+        self.format = format;
+        self
+    }
+
+    /// Write exactly these columns, in this order.
+    pub fn columns<S: Into<String>>(mut self, columns: Vec<S>) -> CsvWriter<W> {
+        self.columns = Some(columns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Take the columns from the keys of the first `records` records, in the
+    /// order they first appear. These records are buffered until the header
+    /// is written. Ignored when the columns are given; at least 1.
+    pub fn infer_columns(mut self, records: usize) -> CsvWriter<W> {
+        self.infer = cmp::max(records, 1);
+        self
+    }
+
+    /// Whether to start with a row of column names, on by default.
+    pub fn header(mut self, header: bool) -> CsvWriter<W> {
+        self.header = header;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    /// Converts every record read by `p`, returning the number of rows
+    /// written, not counting the header. Writes nothing when there are no
+    /// records and no columns were given.
+    pub fn write_records<B: BufRead>(&mut self, p: &mut Parser<B>) -> Result<u64> {
+        let mut records = Records {
+            p,
+            in_array: false,
+        };
+        let mut buffered = Vec::new();
+        let columns = match self.columns.take() {
+            Some(columns) => columns,
+            None => {
+                while buffered.len() < self.infer {
+                    match records.next_record()? {
+                        Some(record) => buffered.push(record),
+                        None => break,
+                    }
+                }
+                if buffered.is_empty() {
+                    // No records, and no columns for a header either.
+                    return Ok(0);
+                }
+                infer(&buffered)
+            }
+        };
+        if self.header {
+            self.write_row(columns.iter().map(|c| c.as_str()))?;
+        }
+        let mut rows = 0;
+        for record in buffered {
+            self.write_record(&columns, record)?;
+            rows += 1;
+        }
+        while let Some(record) = records.next_record()? {
+            self.write_record(&columns, record)?;
+            rows += 1;
+        }
+        self.columns = Some(columns);
+        Ok(rows)
+    }
+
+    fn write_record(&mut self, columns: &[String], record: Record) -> Result<()> {
+        let mut cells: HashMap<String, String> = record.into_iter().collect();
+        let row: Vec<String> = columns.iter()
+            .map(|c| cells.remove(c).unwrap_or_default())
+            .collect();
+        self.write_row(row.iter().map(|c| c.as_str()))
+    }
+
+    fn write_row<'a, I: Iterator<Item = &'a str>>(&mut self, cells: I) -> Result<()> {
+        for (i, cell) in cells.enumerate() {
+            if i > 0 {
+                self.w.write_all(if self.format == Format::Csv { b"," } else { b"\t" })?;
+            }
+            match self.format {
+                Format::Csv => write_csv_cell(&mut self.w, cell)?,
+                Format::Tsv => write_tsv_cell(&mut self.w, cell)?,
+            }
+        }
+        self.w.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// The column names of `records` in the order they first appear.
+fn infer(records: &[Record]) -> Vec<String> {
+    let mut columns: Vec<String> = Vec::new();
+    for record in records {
+        for (name, _) in record {
+            if !columns.contains(name) {
+                columns.push(name.clone());
+            }
+        }
+    }
+    columns
+}
+
+fn write_csv_cell<W: Write>(w: &mut W, cell: &str) -> ::std::io::Result<()> {
+    if !cell.contains([',', '"', '\n', '\r']) {
+        return w.write_all(cell.as_bytes());
+    }
+    w.write_all(b"\"")?;
+    w.write_all(cell.replace('"', "\"\"").as_bytes())?;
+    w.write_all(b"\"")
+}
+
+fn write_tsv_cell<W: Write>(w: &mut W, cell: &str) -> ::std::io::Result<()> {
+    let bytes = cell.as_bytes();
+    let mut start = 0;
+    for (i, &c) in bytes.iter().enumerate() {
+        let escape: &[u8] = match c {
+            b'\t' => b"\\t",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\\' => b"\\\\",
+            _ => continue,
+        };
+        w.write_all(&bytes[start..i])?;
+        w.write_all(escape)?;
+        start = i + 1;
+    }
+    w.write_all(&bytes[start..])
+}
+
+/// Reads records, unwrapping top-level arrays.
+struct Records<'a, B: BufRead + 'a> {
+    p: &'a mut Parser<B>,
+    in_array: bool,
+}
+
+impl<'a, B: BufRead> Records<'a, B> {
+    fn next_record(&mut self) -> Result<Option<Record>> {
+        loop {
+            let ev = match self.p.next() {
+                Some(ev) => ev?,
+                None => return Ok(None),
+            };
+            match ev {
+                Event::Start(Block::Array) if !self.in_array => self.in_array = true,
+                Event::End(Block::Array) if self.in_array => self.in_array = false,
+                Event::Start(Block::Object) => return self.read_object().map(Some),
+                ev => return Err(self.p.unexpected("an object record", Some(&ev))),
+            }
+        }
+    }
+
+    /// Reads the members of an object whose start has been read.
+    fn read_object(&mut self) -> Result<Record> {
+        let mut record = Vec::new();
+        // Names of the enclosing objects, each followed by a `.`.
+        let mut prefix = String::new();
+        let mut lengths = Vec::new();
+        let mut name = String::new();
+        let mut chunks: Option<String> = None;
+        loop {
+            let ev = match self.p.next() {
+                Some(ev) => ev?,
+                None => return Err("EOF".into()),
+            };
+            let cell = match ev {
+                Event::Key(_) | Event::InternedKey(_) | Event::RawKey(_) => {
+                    name = prefix.clone();
+                    match ev {
+                        Event::RawKey(ref k) => name.push_str(&String::from_utf8_lossy(k)),
+                        ref ev => name.push_str(ev.key().unwrap()),
+                    }
+                    continue;
+                }
+                Event::Start(Block::Object) => {
+                    lengths.push(prefix.len());
+                    prefix = mem::take(&mut name);
+                    prefix.push('.');
+                    continue;
+                }
+                Event::End(Block::Object) => {
+                    match lengths.pop() {
+                        Some(len) => prefix.truncate(len),
+                        None => return Ok(record),
+                    }
+                    continue;
+                }
+                Event::Start(Block::Array) => self.read_array()?,
+                Event::End(Block::Array) => unreachable!(),
+                Event::String(s) => s,
+                Event::Bytes(ref b) => String::from_utf8_lossy(b).into_owned(),
+                Event::StringChunk(s) => {
+                    chunks.get_or_insert_with(String::new).push_str(&s);
+                    continue;
+                }
+                Event::StringEnd => chunks.take().unwrap_or_default(),
+                Event::Null => String::new(),
+                ev @ Event::Number(_) | ev @ Event::Bool(_) => {
+                    let mut w = JsonWriter::new(Vec::new());
+                    w.write_event(&ev)?;
+                    String::from_utf8(w.into_inner()).unwrap()
+                }
+            };
+            record.push((mem::take(&mut name), cell));
+        }
+    }
+
+    /// Reads the rest of an array as compact JSON text.
+    fn read_array(&mut self) -> Result<String> {
+        let mut w = JsonWriter::new(Vec::new());
+        self.p.copy_value_to(Event::Start(Block::Array), &mut w)?;
+        Ok(String::from_utf8(w.into_inner()).unwrap())
+    }
+}
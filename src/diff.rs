@@ -0,0 +1,319 @@
+//! Structural differences between two documents.
+//!
+//! [`diff`] reads two parsers in lockstep and only holds on to what differs.
+//! Arrays are compared element by element, and objects member by member for
+//! as long as their keys come in the same order. From the first key that
+//! does not match, the rest of both objects is read into [`Value`]s and
+//! compared by key. Values of different types are replaced whole.
+//!
+//! The changes form a [JSON Patch](https://www.rfc-editor.org/rfc/rfc6902)
+//! that turns the first document into the second:
+//!
+//! ```
+//! use jsonpull::{diff, Parser};
+//!
+//! let mut a = Parser::from_reader(&br#"{"id": 1, "tags": ["x", "y"]}"#[..]);
+//! let mut b = Parser::from_reader(&br#"{"id": 2, "tags": ["x"]}"#[..]);
+//! let changes = diff(&mut a, &mut b).unwrap();
+//! assert_eq!(changes[0].to_string(), "~ /id: 1 -> 2");
+//! assert_eq!(changes[1].to_string(), "- /tags/1: \"y\"");
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{BufRead, Write};
+
+use path::escape_token;
+use value::{read_value, Value};
+use writer::JsonWriter;
+use {Block, Event, Parser, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Remove,
+    Replace,
+}
+
+/// One difference between the documents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub op: Op,
+    /// JSON Pointer to the value, in the document as patched by the changes
+    /// before this one. Removing the last two of three elements is two
+    /// removals of `/1`.
+    pub path: String,
+    /// The value in the first document, for `Remove` and `Replace`.
+    pub old: Option<Value>,
+    /// The value in the second document, for `Add` and `Replace`.
+    pub new: Option<Value>,
+}
+
+impl Change {
+    /// Writes the change as a JSON Patch operation.
+    pub fn write<W: Write>(&self, w: &mut JsonWriter<W>) -> Result<()> {
+        let op = match self.op {
+            Op::Add => "add",
+            Op::Remove => "remove",
+            Op::Replace => "replace",
+        };
+        w.write_event(&Event::Start(Block::Object))?;
+        w.write_event(&Event::Key("op".into()))?;
+        w.write_event(&Event::String(op.into()))?;
+        w.write_event(&Event::Key("path".into()))?;
+        w.write_event(&Event::String(self.path.clone()))?;
+        if let Some(ref new) = self.new {
+            w.write_event(&Event::Key("value".into()))?;
+            new.write(w)?;
+        }
+        w.write_event(&Event::End(Block::Object))
+    }
+}
+
+/// A line of a report: `+ /path: new`, `- /path: old` or
+/// `~ /path: old -> new`.
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = if self.path.is_empty() { "(root)" } else { &self.path };
+        match (self.op, &self.old, &self.new) {
+            (Op::Add, _, Some(new)) => write!(f, "+ {}: {}", path, new),
+            (Op::Remove, Some(old), _) => write!(f, "- {}: {}", path, old),
+            (Op::Replace, Some(old), Some(new)) => write!(f, "~ {}: {} -> {}", path, old, new),
+            (op, _, _) => write!(f, "{:?} {}", op, path),
+        }
+    }
+}
+
+/// Writes `changes` as a JSON Patch document.
+pub fn write_patch<W: Write>(changes: &[Change], w: &mut JsonWriter<W>) -> Result<()> {
+    w.write_event(&Event::Start(Block::Array))?;
+    for change in changes {
+        change.write(w)?;
+    }
+    w.write_event(&Event::End(Block::Array))
+}
+
+/// The changes from the first value of `a` to the first value of `b`.
+pub fn diff<A: BufRead, B: BufRead>(a: &mut Parser<A>, b: &mut Parser<B>) -> Result<Vec<Change>> {
+    let mut changes = Vec::new();
+    diff_with(a, b, |change| {
+        changes.push(change);
+        true
+    })?;
+    Ok(changes)
+}
+
+/// Compares the first values of `a` and `b`, passing each change to `f` as
+/// soon as it is found. Stops early when `f` returns `false`. Returns whether
+/// the values were equal.
+pub fn diff_with<A, B, F>(a: &mut Parser<A>, b: &mut Parser<B>, f: F) -> Result<bool>
+    where A: BufRead,
+          B: BufRead,
+          F: FnMut(Change) -> bool
+{
+    let mut differ = Differ {
+        a,
+        b,
+        f,
+        equal: true,
+    };
+    differ.value(&mut String::new())?;
+    Ok(differ.equal)
+}
+
+struct Differ<'a, 'b, A: BufRead + 'a, B: BufRead + 'b, F> {
+    a: &'a mut Parser<A>,
+    b: &'b mut Parser<B>,
+    f: F,
+    equal: bool,
+}
+
+impl<'a, 'b, A: BufRead, B: BufRead, F: FnMut(Change) -> bool> Differ<'a, 'b, A, B, F> {
+    /// Reports a change, returning whether to go on.
+    fn emit(&mut self, op: Op, path: &str, old: Option<Value>, new: Option<Value>) -> bool {
+        self.equal = false;
+        let change = Change {
+            op,
+            path: path.to_string(),
+            old,
+            new,
+        };
+        (self.f)(change)
+    }
+
+    /// Compares the next values of both parsers. `Ok(false)` means stop.
+    fn value(&mut self, path: &mut String) -> Result<bool> {
+        let ea = next(self.a)?;
+        let eb = next(self.b)?;
+        match (ea, eb) {
+            (Event::Start(Block::Array), Event::Start(Block::Array)) => self.array(path),
+            (Event::Start(Block::Object), Event::Start(Block::Object)) => self.object(path),
+            (ea, eb) => {
+                let old = read_value(self.a, ea)?;
+                let new = read_value(self.b, eb)?;
+                Ok(old == new || self.emit(Op::Replace, path, Some(old), Some(new)))
+            }
+        }
+    }
+
+    fn array(&mut self, path: &mut String) -> Result<bool> {
+        let len = path.len();
+        let mut index = 0;
+        loop {
+            let a_end = matches!(self.a.peek()?, Some(&Event::End(_)));
+            let b_end = matches!(self.b.peek()?, Some(&Event::End(_)));
+            path.push_str(&format!("/{}", index));
+            let go_on = match (a_end, b_end) {
+                (true, true) => {
+                    next(self.a)?;
+                    next(self.b)?;
+                    path.truncate(len);
+                    return Ok(true);
+                }
+                (false, false) => {
+                    index += 1;
+                    self.value(path)?
+                }
+                (false, true) => {
+                    let ev = next(self.a)?;
+                    let old = read_value(self.a, ev)?;
+                    self.emit(Op::Remove, path, Some(old), None)
+                }
+                (true, false) => {
+                    index += 1;
+                    let ev = next(self.b)?;
+                    let new = read_value(self.b, ev)?;
+                    self.emit(Op::Add, path, None, Some(new))
+                }
+            };
+            path.truncate(len);
+            if !go_on {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn object(&mut self, path: &mut String) -> Result<bool> {
+        let len = path.len();
+        loop {
+            let ka = next_key(self.a)?;
+            let kb = next_key(self.b)?;
+            match (ka, kb) {
+                (None, None) => return Ok(true),
+                (Some(ka), Some(kb)) if ka == kb => {
+                    path.push('/');
+                    path.push_str(&escape_token(&ka));
+                    let go_on = self.value(path)?;
+                    path.truncate(len);
+                    if !go_on {
+                        return Ok(false);
+                    }
+                }
+                (ka, kb) => {
+                    let old = read_members(self.a, ka)?;
+                    let new = read_members(self.b, kb)?;
+                    return Ok(self.members(path, &old, &new));
+                }
+            }
+        }
+    }
+
+    /// Compares values that have been read.
+    fn values(&mut self, path: &mut String, old: &Value, new: &Value) -> bool {
+        match (old, new) {
+            (Value::Array(old), Value::Array(new)) => {
+                let len = path.len();
+                for (index, (old, new)) in old.iter().zip(new).enumerate() {
+                    path.push_str(&format!("/{}", index));
+                    let go_on = self.values(path, old, new);
+                    path.truncate(len);
+                    if !go_on {
+                        return false;
+                    }
+                }
+                path.push_str(&format!("/{}", new.len().min(old.len())));
+                let go_on = old.iter()
+                    .skip(new.len())
+                    .all(|old| self.emit(Op::Remove, path, Some(old.clone()), None));
+                path.truncate(len);
+                if !go_on {
+                    return false;
+                }
+                for (index, new) in new.iter().enumerate().skip(old.len()) {
+                    path.push_str(&format!("/{}", index));
+                    let go_on = self.emit(Op::Add, path, None, Some(new.clone()));
+                    path.truncate(len);
+                    if !go_on {
+                        return false;
+                    }
+                }
+                true
+            }
+            (Value::Object(old), Value::Object(new)) => self.members(path, old, new),
+            _ => old == new || self.emit(Op::Replace, path, Some(old.clone()), Some(new.clone())),
+        }
+    }
+
+    /// Compares object members by key. With duplicate keys, the last one
+    /// counts.
+    fn members(&mut self,
+               path: &mut String,
+               old: &[(String, Value)],
+               new: &[(String, Value)])
+               -> bool {
+        let old_by_key: HashMap<&str, &Value> = old.iter().map(|m| (&*m.0, &m.1)).collect();
+        let new_by_key: HashMap<&str, &Value> = new.iter().map(|m| (&*m.0, &m.1)).collect();
+        let len = path.len();
+        let mut seen = HashSet::new();
+        for key in old.iter().chain(new).map(|m| &*m.0) {
+            if !seen.insert(key) {
+                continue;
+            }
+            path.push('/');
+            path.push_str(&escape_token(key));
+            let go_on = match (old_by_key.get(key), new_by_key.get(key)) {
+                (Some(old), Some(new)) => self.values(path, old, new),
+                (Some(old), None) => self.emit(Op::Remove, path, Some((*old).clone()), None),
+                (None, Some(new)) => self.emit(Op::Add, path, None, Some((*new).clone())),
+                (None, None) => unreachable!(),
+            };
+            path.truncate(len);
+            if !go_on {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn next<B: BufRead>(p: &mut Parser<B>) -> Result<Event> {
+    match p.next() {
+        Some(ev) => ev,
+        None => Err("EOF".into()),
+    }
+}
+
+/// The next key of an object, or `None` at its end.
+fn next_key<B: BufRead>(p: &mut Parser<B>) -> Result<Option<String>> {
+    match next(p)? {
+        Event::Key(k) => Ok(Some(k)),
+        Event::InternedKey(k) => Ok(Some(k.to_string())),
+        Event::RawKey(k) => Ok(Some(String::from_utf8_lossy(&k).into_owned())),
+        Event::End(_) => Ok(None),
+        ev => Err(format!("expected a key, found {:?}", ev).into()),
+    }
+}
+
+/// Reads the rest of an object, starting with the member `key` if the key
+/// has already been read.
+fn read_members<B: BufRead>(p: &mut Parser<B>,
+                            mut key: Option<String>)
+                            -> Result<Vec<(String, Value)>> {
+    let mut members = Vec::new();
+    while let Some(k) = key {
+        let ev = next(p)?;
+        members.push((k, read_value(p, ev)?));
+        key = next_key(p)?;
+    }
+    Ok(members)
+}
@@ -0,0 +1,228 @@
+//! Detecting and transcoding UTF-16 and UTF-32 input.
+//!
+//! [`Decoder`] wraps a reader of JSON text in any of the encodings allowed
+//! by [RFC 4627](https://tools.ietf.org/html/rfc4627#section-3), detected
+//! from a byte order mark or from the pattern of zero bytes at the start of
+//! the input, and presents it to the parser as UTF-8. Offsets reported by a
+//! parser over a `Decoder` count bytes of the transcoded UTF-8.
+
+use std::io::{self, BufRead, Read};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Be,
+    Utf16Le,
+    Utf32Be,
+    Utf32Le,
+}
+
+impl Encoding {
+    /// Detects the encoding of input starting with `head`, which should
+    /// hold the first four bytes if there are that many. Returns the length
+    /// of the byte order mark to skip as well.
+    pub fn detect(head: &[u8]) -> (Encoding, usize) {
+        match *head {
+            [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8, 3),
+            [0x00, 0x00, 0xFE, 0xFF, ..] => (Encoding::Utf32Be, 4),
+            [0xFF, 0xFE, 0x00, 0x00, ..] => (Encoding::Utf32Le, 4),
+            [0xFE, 0xFF, ..] => (Encoding::Utf16Be, 2),
+            [0xFF, 0xFE, ..] => (Encoding::Utf16Le, 2),
+            // The first character of JSON text is ASCII, so the position of
+            // the zero bytes around it gives the encoding away.
+            [0x00, 0x00, 0x00, _, ..] => (Encoding::Utf32Be, 0),
+            [_, 0x00, 0x00, 0x00, ..] => (Encoding::Utf32Le, 0),
+            [0x00, _, ..] => (Encoding::Utf16Be, 0),
+            [_, 0x00, ..] => (Encoding::Utf16Le, 0),
+            _ => (Encoding::Utf8, 0),
+        }
+    }
+
+    fn unit_len(self) -> usize {
+        match self {
+            Encoding::Utf8 => 1,
+            Encoding::Utf16Be | Encoding::Utf16Le => 2,
+            Encoding::Utf32Be | Encoding::Utf32Le => 4,
+        }
+    }
+}
+
+const CHUNK: usize = 8 * 1024;
+
+/// Transcodes its input to UTF-8.
+#[derive(Debug)]
+pub struct Decoder<R: BufRead> {
+    inner: R,
+    encoding: Encoding,
+    /// Bytes read from `inner` but not decoded yet.
+    raw: Vec<u8>,
+    out: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: BufRead> Decoder<R> {
+    /// Detects the encoding from the start of `inner` and skips the byte
+    /// order mark if there is one.
+    pub fn new(mut inner: R) -> io::Result<Decoder<R>> {
+        let mut head = Vec::with_capacity(4);
+        while head.len() < 4 {
+            let n = {
+                let data = inner.fill_buf()?;
+                if data.is_empty() {
+                    break;
+                }
+                let n = ::std::cmp::min(4 - head.len(), data.len());
+                head.extend_from_slice(&data[..n]);
+                n
+            };
+            inner.consume(n);
+        }
+        let (encoding, bom) = Encoding::detect(&head);
+        let mut decoder = Decoder::with_encoding(inner, encoding);
+        decoder.raw = head.split_off(bom);
+        Ok(decoder)
+    }
+
+    /// Decodes `inner` as `encoding`, without looking for a byte order mark.
+    pub fn with_encoding(inner: R, encoding: Encoding) -> Decoder<R> {
+        Decoder {
+            inner,
+            encoding,
+            raw: Vec::new(),
+            out: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    fn passthrough(&self) -> bool {
+        self.encoding == Encoding::Utf8 && self.raw.is_empty()
+    }
+
+    /// Decodes the next chunk of input into `out`, which must be empty.
+    /// Leaves it empty at EOF.
+    fn refill(&mut self) -> io::Result<()> {
+        self.out.clear();
+        self.pos = 0;
+        if self.encoding == Encoding::Utf8 {
+            ::std::mem::swap(&mut self.out, &mut self.raw);
+            return Ok(());
+        }
+        while self.out.is_empty() {
+            let n = {
+                let data = self.inner.fill_buf()?;
+                let n = ::std::cmp::min(data.len(), CHUNK);
+                self.raw.extend_from_slice(&data[..n]);
+                n
+            };
+            self.inner.consume(n);
+            let eof = n == 0;
+            let used = self.decode(eof)?;
+            self.raw.drain(..used);
+            if eof {
+                if !self.raw.is_empty() {
+                    return Err(invalid("truncated code unit at end of input"));
+                }
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes the complete characters in `raw`, returning how many bytes
+    /// were used.
+    fn decode(&mut self, eof: bool) -> io::Result<usize> {
+        let unit_len = self.encoding.unit_len();
+        let units = self.raw.len() / unit_len;
+        let mut used = 0;
+        let mut buf = [0; 4];
+        let mut i = 0;
+        while i < units {
+            let at = i * unit_len;
+            let c = match self.encoding {
+                Encoding::Utf16Be | Encoding::Utf16Le => {
+                    let first = self.unit16(at);
+                    if (0xD800..=0xDBFF).contains(&first) {
+                        if i + 1 == units {
+                            if eof {
+                                return Err(invalid("unpaired surrogate"));
+                            }
+                            break;
+                        }
+                        i += 1;
+                        let second = self.unit16(at + 2);
+                        ::std::char::decode_utf16([first, second].iter().cloned())
+                            .next()
+                            .unwrap()
+                            .map_err(|_| invalid("unpaired surrogate"))?
+                    } else {
+                        ::std::char::from_u32(first as u32)
+                            .ok_or_else(|| invalid("unpaired surrogate"))?
+                    }
+                }
+                _ => {
+                    let b = &self.raw[at..at + 4];
+                    let n = if self.encoding == Encoding::Utf32Be {
+                        u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+                    } else {
+                        u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+                    };
+                    ::std::char::from_u32(n).ok_or_else(|| invalid("invalid code point"))?
+                }
+            };
+            self.out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            i += 1;
+            used = i * unit_len;
+        }
+        Ok(used)
+    }
+
+    fn unit16(&self, at: usize) -> u16 {
+        let b = [self.raw[at], self.raw[at + 1]];
+        if self.encoding == Encoding::Utf16Be {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        }
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl<R: BufRead> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = {
+            let data = self.fill_buf()?;
+            let n = ::std::cmp::min(data.len(), buf.len());
+            buf[..n].copy_from_slice(&data[..n]);
+            n
+        };
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Decoder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.out.len() {
+            if self.passthrough() {
+                return self.inner.fill_buf();
+            }
+            self.refill()?;
+        }
+        Ok(&self.out[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.pos == self.out.len() && self.passthrough() {
+            self.inner.consume(amt);
+        } else {
+            self.pos += amt;
+        }
+    }
+}
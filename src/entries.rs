@@ -0,0 +1,243 @@
+//! Iterating over the members of an object or the elements of an array.
+//!
+//! [`Parser::object_entries`] and [`Parser::array_elements`] hand out a
+//! [`ValueHandle`] for every value, which dereferences to the parser so the
+//! value can be read, skipped or descended into with any of its methods.
+//! Whatever is left of the value when the handle is dropped is skipped, so
+//! the iteration always continues with the next member. An error hit while
+//! skipping is returned by the next call to the iterator.
+//!
+//! ```
+//! # use jsonpull::*;
+//! let mut p = Parser::from_reader(r#"{"a": 1, "b": [2, 3], "c": 4}"#.as_bytes());
+//! let mut sum = 0;
+//! let mut entries = p.object_entries().unwrap();
+//! while let Some((key, mut value)) = entries.next_entry().unwrap() {
+//!     if key != "b" {
+//!         sum += value.read_u64().unwrap();
+//!     }
+//! }
+//! assert_eq!(sum, 5);
+//! ```
+
+use std::io::BufRead;
+use std::ops::{Deref, DerefMut};
+
+use {Error, Event, Kind, Parser, Result};
+
+/// The members of an object, see [`Parser::object_entries`].
+#[derive(Debug)]
+pub struct ObjectEntries<'a, B: BufRead + 'a> {
+    parser: &'a mut Parser<B>,
+    /// Where the object is, to skip the rest of it when dropped early.
+    end: End,
+    error: Option<Error>,
+    done: bool,
+}
+
+/// The elements of an array, see [`Parser::array_elements`].
+#[derive(Debug)]
+pub struct ArrayElements<'a, B: BufRead + 'a> {
+    parser: &'a mut Parser<B>,
+    end: End,
+    error: Option<Error>,
+    done: bool,
+}
+
+/// One value of an object or array, dereferencing to the parser.
+#[derive(Debug)]
+pub struct ValueHandle<'a, B: BufRead + 'a> {
+    parser: &'a mut Parser<B>,
+    end: End,
+    error: &'a mut Option<Error>,
+}
+
+/// Identifies the end of a value by the container it is in and the number
+/// of values read from that container before it.
+#[derive(Debug, Clone, Copy)]
+struct End {
+    depth: usize,
+    values: usize,
+}
+
+impl End {
+    /// The end of the value whose first event is the next one.
+    fn next_value<B: BufRead>(parser: &Parser<B>) -> End {
+        let depth = parser.depth();
+        End {
+            depth,
+            values: parser.containers[depth].values,
+        }
+    }
+
+    /// The end of the container that was just started.
+    fn current_container<B: BufRead>(parser: &Parser<B>) -> End {
+        let depth = parser.depth() - 1;
+        End {
+            depth,
+            values: parser.containers[depth].values,
+        }
+    }
+
+    fn reached<B: BufRead>(&self, parser: &Parser<B>) -> bool {
+        match parser.containers.get(self.depth) {
+            Some(c) => c.values > self.values,
+            None => true,
+        }
+    }
+
+    /// Reads events up to the end of the value.
+    fn skip<B: BufRead>(&self, parser: &mut Parser<B>) -> Result<()> {
+        if self.reached(parser) {
+            // A peeked event that finished the value belongs to it, while
+            // any other is the start of what comes after it.
+            let finishes = match parser.peeked {
+                Some(Event::Start(_)) | Some(Event::Key(_)) | Some(Event::InternedKey(_)) |
+                Some(Event::RawKey(_)) | Some(Event::StringChunk(_)) | None => false,
+                Some(_) => true,
+            };
+            if finishes && parser.depth() == self.depth &&
+               parser.containers[self.depth].values == self.values + 1 {
+                parser.peeked = None;
+            }
+            return Ok(());
+        }
+        while !self.reached(parser) {
+            match parser.next_raw() {
+                Some(Ok(_)) => (),
+                Some(Err(e)) => return Err(e),
+                None => return Err("EOF".into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<B: BufRead> Parser<B> {
+    /// Reads the start of an object and returns its members one at a time.
+    /// Dropping the iterator before the end skips the rest of the object,
+    /// ignoring any errors in it.
+    pub fn object_entries(&mut self) -> Result<ObjectEntries<'_, B>> {
+        self.expect_object_start()?;
+        Ok(ObjectEntries {
+            end: End::current_container(self),
+            parser: self,
+            error: None,
+            done: false,
+        })
+    }
+
+    /// Reads the start of an array and returns its elements one at a time.
+    /// Dropping the iterator before the end skips the rest of the array,
+    /// ignoring any errors in it.
+    pub fn array_elements(&mut self) -> Result<ArrayElements<'_, B>> {
+        self.expect_array_start()?;
+        Ok(ArrayElements {
+            end: End::current_container(self),
+            parser: self,
+            error: None,
+            done: false,
+        })
+    }
+}
+
+impl<'a, B: BufRead> ObjectEntries<'a, B> {
+    /// The key and value of the next member, or `None` after the end of the
+    /// object has been read.
+    pub fn next_entry(&mut self) -> Result<Option<(String, ValueHandle<'_, B>)>> {
+        if let Some(e) = self.error.take() {
+            self.done = true;
+            return Err(e);
+        }
+        if self.done {
+            return Ok(None);
+        }
+        let key = match self.parser.next_key() {
+            Ok(Some(key)) => key,
+            Ok(None) => {
+                self.done = true;
+                return Ok(None);
+            }
+            Err(e) => {
+                self.done = true;
+                return Err(e);
+            }
+        };
+        Ok(Some((key,
+                 ValueHandle {
+                     end: End::next_value(self.parser),
+                     parser: self.parser,
+                     error: &mut self.error,
+                 })))
+    }
+}
+
+impl<'a, B: BufRead> ArrayElements<'a, B> {
+    /// The next element, or `None` after the end of the array has been read.
+    pub fn next_element(&mut self) -> Result<Option<ValueHandle<'_, B>>> {
+        if let Some(e) = self.error.take() {
+            self.done = true;
+            return Err(e);
+        }
+        if self.done {
+            return Ok(None);
+        }
+        match self.parser.peek_kind() {
+            Ok(Some(Kind::End)) => {
+                self.done = true;
+                self.parser.expect_array_end()?;
+                Ok(None)
+            }
+            Ok(_) => {
+                Ok(Some(ValueHandle {
+                    end: End::next_value(self.parser),
+                    parser: self.parser,
+                    error: &mut self.error,
+                }))
+            }
+            Err(e) => {
+                self.done = true;
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<'a, B: BufRead> Drop for ObjectEntries<'a, B> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.end.skip(self.parser);
+        }
+    }
+}
+
+impl<'a, B: BufRead> Drop for ArrayElements<'a, B> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.end.skip(self.parser);
+        }
+    }
+}
+
+impl<'a, B: BufRead> Drop for ValueHandle<'a, B> {
+    fn drop(&mut self) {
+        if let Err(e) = self.end.skip(self.parser) {
+            *self.error = Some(e);
+        }
+    }
+}
+
+impl<'a, B: BufRead> Deref for ValueHandle<'a, B> {
+    type Target = Parser<B>;
+
+    fn deref(&self) -> &Parser<B> {
+        self.parser
+    }
+}
+
+impl<'a, B: BufRead> DerefMut for ValueHandle<'a, B> {
+    fn deref_mut(&mut self) -> &mut Parser<B> {
+        self.parser
+    }
+}
+
@@ -4,10 +4,20 @@ error_chain! {
             description("invalid syntax")
             display("expected '{:?}', found '{}'", excped, found)
         }
+        Unexpected(expected: &'static str, found: String, at: ::Position) {
+            description("unexpected event")
+            display("expected {}, found {} at line {}, offset {}",
+                    expected, found, at.line, at.offset)
+        }
+        DuplicateKey(key: String, at: ::Position) {
+            description("duplicate key")
+            display("duplicate key {:?} at line {}, offset {}", key, at.line, at.offset)
+        }
     }
 
     foreign_links {
         Utf8(::std::string::FromUtf8Error);
+        Utf8Str(::std::str::Utf8Error);
         Io(::std::io::Error);
         Unicode(::std::num::ParseIntError);
     }
@@ -0,0 +1,152 @@
+//! Byte-offset indexes for random access into large documents.
+//!
+//! An [`Index`] is built with one pass of a [`Parser`] over the input and maps
+//! the JSON Pointer of every value up to a given depth to the offset where
+//! that value starts. It can be saved next to the data and later used to
+//! open a parser directly at one of the values:
+//!
+//! ```no_run
+//! # use std::fs::File;
+//! # use std::io::BufReader;
+//! # use jsonpull::Parser;
+//! # use jsonpull::index::IndexBuilder;
+//! # fn run() -> jsonpull::Result<()> {
+//! let f = BufReader::new(File::open("records.json")?);
+//! let index = IndexBuilder::new().build(&mut Parser::from_reader(f))?;
+//!
+//! let f = BufReader::new(File::open("records.json")?);
+//! let record = index.open(f, "/123456")?.expect("no such record");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::io::{BufRead, Read, Seek, Write};
+
+use path::{PathTracker, Step};
+use {Parser, Result};
+
+const MAGIC: &[u8] = b"JPIDX\x01";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub pointer: String,
+    pub offset: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    entries: Vec<Entry>,
+    lookup: HashMap<String, usize>,
+}
+
+impl Index {
+    fn push(&mut self, entry: Entry) {
+        self.lookup.insert(entry.pointer.clone(), self.entries.len());
+        self.entries.push(entry);
+    }
+
+    /// Indexed values in document order.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn get(&self, pointer: &str) -> Option<u64> {
+        self.lookup.get(pointer).map(|&i| self.entries[i].offset)
+    }
+
+    /// Offset of the `n`-th element of a top-level array.
+    pub fn element(&self, n: usize) -> Option<u64> {
+        self.get(&format!("/{}", n))
+    }
+
+    /// Opens a parser for the value at `pointer` over `reader`, which must
+    /// read the same input the index was built from.
+    pub fn open<R: BufRead + Seek>(&self, reader: R, pointer: &str) -> Result<Option<Parser<R>>> {
+        match self.get(pointer) {
+            Some(offset) => Parser::from_reader_at(reader, offset).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, mut w: W) -> Result<()> {
+        w.write_all(MAGIC)?;
+        for entry in &self.entries {
+            w.write_all(&entry.offset.to_le_bytes())?;
+            w.write_all(&(entry.pointer.len() as u32).to_le_bytes())?;
+            w.write_all(entry.pointer.as_bytes())?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(mut r: R) -> Result<Index> {
+        let mut magic = [0; 6];
+        r.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err("not a jsonpull index".into());
+        }
+        let mut index = Index::default();
+        let mut offset = [0; 8];
+        loop {
+            match r.read(&mut offset[..1])? {
+                0 => break,
+                _ => r.read_exact(&mut offset[1..])?,
+            }
+            let mut len = [0; 4];
+            r.read_exact(&mut len)?;
+            let mut pointer = vec![0; u32::from_le_bytes(len) as usize];
+            r.read_exact(&mut pointer)?;
+            index.push(Entry {
+                pointer: String::from_utf8(pointer)?,
+                offset: u64::from_le_bytes(offset),
+            });
+        }
+        Ok(index)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexBuilder {
+    max_depth: usize,
+}
+
+impl Default for IndexBuilder {
+    fn default() -> IndexBuilder {
+        IndexBuilder::new()
+    }
+}
+
+impl IndexBuilder {
+    /// A builder that indexes the elements (or members) of the top-level
+    /// value.
+    pub fn new() -> IndexBuilder {
+        IndexBuilder { max_depth: 1 }
+    }
+
+    /// Index values nested at most `depth` levels deep. `0` only indexes the
+    /// root value.
+    pub fn max_depth(mut self, depth: usize) -> IndexBuilder {
+        self.max_depth = depth;
+        self
+    }
+
+    pub fn build<B: BufRead>(&self, parser: &mut Parser<B>) -> Result<Index> {
+        let mut index = Index::default();
+        let mut path = PathTracker::new();
+        while let Some(ev) = parser.next() {
+            // A chunked string is indexed where its first chunk starts.
+            match path.event(&ev?) {
+                Step::Scalar | Step::Open => (),
+                _ => continue,
+            }
+            if path.depth() <= self.max_depth {
+                index.push(Entry {
+                    pointer: path.pointer(),
+                    offset: parser.event_offset(),
+                });
+            }
+        }
+        Ok(index)
+    }
+}
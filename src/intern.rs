@@ -0,0 +1,41 @@
+//! Sharing the strings of repeated object keys.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// A bounded table of previously seen keys, see `Parser::intern_keys`.
+#[derive(Debug, Clone)]
+pub struct KeyInterner {
+    keys: HashSet<Arc<str>>,
+    max_keys: usize,
+}
+
+impl KeyInterner {
+    pub fn new(max_keys: usize) -> KeyInterner {
+        KeyInterner {
+            keys: HashSet::new(),
+            max_keys,
+        }
+    }
+
+    /// Returns the shared copy of `key`, adding it if there is room left.
+    pub fn intern(&mut self, key: &str) -> Option<Arc<str>> {
+        if let Some(k) = self.keys.get(key) {
+            return Some(k.clone());
+        }
+        if self.keys.len() >= self.max_keys {
+            return None;
+        }
+        let k: Arc<str> = Arc::from(key);
+        self.keys.insert(k.clone());
+        Some(k)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
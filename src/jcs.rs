@@ -0,0 +1,304 @@
+//! Canonical JSON as defined by the JSON Canonicalization Scheme
+//! ([RFC 8785](https://www.rfc-editor.org/rfc/rfc8785)), for hashing and
+//! signing.
+//!
+//! Object members are sorted by the UTF-16 code units of their keys, numbers
+//! are written as ECMAScript writes doubles, strings with the fewest
+//! escapes, and there is no whitespace. Only objects are buffered, one at a
+//! time from the innermost out, until their members can be sorted; arrays
+//! and scalars outside of objects are written as they are read.
+//!
+//! The input must be I-JSON: strings valid UTF-8, no duplicate keys and no
+//! numbers outside the range of a double.
+
+use std::io::{BufRead, Write};
+
+use path::{Shape, Step};
+use writer;
+use {Event, Parser, Result, N};
+
+#[derive(Debug)]
+enum Frame {
+    /// The members read so far, each with its value written out.
+    Object(Vec<(String, Vec<u8>)>),
+    Array { first: bool },
+}
+
+/// Writes events in canonical form, see the [module documentation](index.html).
+///
+/// Top-level values are separated by newlines.
+#[derive(Debug)]
+pub struct Canonicalizer<W: Write> {
+    w: W,
+    stack: Vec<Frame>,
+    shape: Shape,
+    values: usize,
+}
+
+impl<W: Write> Canonicalizer<W> {
+    pub fn new(w: W) -> Canonicalizer<W> {
+        Canonicalizer {
+            w,
+            stack: Vec::new(),
+            shape: Shape::new(),
+            values: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    /// Writes every event of `events`, stopping at the first error.
+    pub fn write_events<I>(&mut self, events: I) -> Result<()>
+        where I: IntoIterator<Item = Result<Event>>
+    {
+        for ev in events {
+            self.write_event(&ev?)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_event(&mut self, ev: &Event) -> Result<()> {
+        let step = self.shape.step(ev);
+        match *ev {
+            Event::Start(::Block::Object) => {
+                self.before_value()?;
+                self.stack.push(Frame::Object(Vec::new()));
+            }
+            Event::Start(::Block::Array) => {
+                self.before_value()?;
+                self.out().write_all(b"[")?;
+                self.stack.push(Frame::Array { first: true });
+            }
+            Event::End(_) => {
+                match self.stack.pop() {
+                    Some(Frame::Object(members)) => self.write_object(members)?,
+                    Some(Frame::Array { .. }) => self.out().write_all(b"]")?,
+                    None => return Err("unbalanced end of container".into()),
+                }
+                self.after_value();
+            }
+            Event::Key(ref k) => self.key(k)?,
+            Event::InternedKey(ref k) => self.key(k)?,
+            Event::RawKey(_) | Event::Bytes(_) => {
+                return Err("canonical JSON needs strings in UTF-8".into())
+            }
+            Event::String(ref s) => {
+                self.before_value()?;
+                write_string(self.out(), s)?;
+                self.after_value();
+            }
+            Event::StringChunk(ref s) => {
+                if step == Step::Open {
+                    self.before_value()?;
+                    self.out().write_all(b"\"")?;
+                }
+                writer::write_escaped(&mut self.out(), s)?;
+            }
+            Event::StringEnd => {
+                self.out().write_all(b"\"")?;
+                self.after_value();
+            }
+            Event::Number(n) => {
+                self.before_value()?;
+                let f = match n {
+                    N::Float(f) => f,
+                    N::Int(i) => i as f64,
+                    N::Uint(u) => u as f64,
+                };
+                let s = format_number(f)?;
+                self.out().write_all(s.as_bytes())?;
+                self.after_value();
+            }
+            Event::Bool(b) => {
+                self.before_value()?;
+                self.out().write_all(if b { b"true" } else { b"false" })?;
+                self.after_value();
+            }
+            Event::Null => {
+                self.before_value()?;
+                self.out().write_all(b"null")?;
+                self.after_value();
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.w.flush()?;
+        Ok(())
+    }
+
+    /// Where the current value goes: the member of the innermost object, or
+    /// the output.
+    fn out(&mut self) -> &mut dyn Write {
+        for frame in self.stack.iter_mut().rev() {
+            if let Frame::Object(ref mut members) = *frame {
+                if let Some(member) = members.last_mut() {
+                    return &mut member.1;
+                }
+            }
+        }
+        &mut self.w
+    }
+
+    fn key(&mut self, k: &str) -> Result<()> {
+        match self.stack.last_mut() {
+            Some(&mut Frame::Object(ref mut members)) => {
+                members.push((k.to_string(), Vec::new()));
+                Ok(())
+            }
+            _ => Err("key outside of an object".into()),
+        }
+    }
+
+    fn before_value(&mut self) -> Result<()> {
+        match self.stack.last_mut() {
+            Some(&mut Frame::Array { ref mut first }) => {
+                if !*first {
+                    self.out().write_all(b",")?;
+                } else {
+                    *first = false;
+                }
+            }
+            Some(&mut Frame::Object(_)) => (),
+            None => {
+                if self.values > 0 {
+                    self.w.write_all(b"\n")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn after_value(&mut self) {
+        if self.stack.is_empty() {
+            self.values += 1;
+        }
+    }
+
+    fn write_object(&mut self, mut members: Vec<(String, Vec<u8>)>) -> Result<()> {
+        members.sort_by(|a, b| a.0.encode_utf16().cmp(b.0.encode_utf16()));
+        if let Some(pair) = members.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(format!("duplicate key {:?}", pair[0].0).into());
+        }
+        let out = self.out();
+        out.write_all(b"{")?;
+        for (i, (key, value)) in members.iter().enumerate() {
+            if i > 0 {
+                out.write_all(b",")?;
+            }
+            write_string(out, key)?;
+            out.write_all(b":")?;
+            out.write_all(value)?;
+        }
+        out.write_all(b"}")?;
+        Ok(())
+    }
+}
+
+fn write_string(mut w: &mut dyn Write, s: &str) -> ::std::io::Result<()> {
+    w.write_all(b"\"")?;
+    writer::write_escaped(&mut w, s)?;
+    w.write_all(b"\"")
+}
+
+/// Reads all values of `p` and writes them in canonical form.
+pub fn canonicalize<B: BufRead, W: Write>(p: &mut Parser<B>, w: W) -> Result<W> {
+    let mut c = Canonicalizer::new(w);
+    c.write_events(p)?;
+    Ok(c.into_inner())
+}
+
+/// `json` in canonical form.
+pub fn to_canonical_string(json: &str) -> Result<String> {
+    let mut p = Parser::from_reader(json.as_bytes());
+    let out = canonicalize(&mut p, Vec::new())?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+/// Formats a double as ECMAScript's `Number.prototype.toString` does, which
+/// is the shortest decimal that reads back as the same double, written
+/// without an exponent between 1e-7 and 1e21.
+pub fn format_number(f: f64) -> Result<String> {
+    if !f.is_finite() {
+        return Err("NaN and infinities cannot be written as JSON".into());
+    }
+    if f == 0.0 {
+        // Including -0.
+        return Ok("0".into());
+    }
+    // Shortest round-trip digits, like "1.2345e-7".
+    let sci = format!("{:e}", f.abs());
+    let e = sci.find('e').unwrap();
+    let mut digits: String = sci[..e].chars().filter(|&c| c != '.').collect();
+    let exponent: i32 = sci[e + 1..].parse().unwrap();
+    // Two candidates can only be equally close with 16 digits or more.
+    if digits.len() >= 16 && digits.ends_with(['1', '3', '5', '7', '9']) {
+        if let Some(even) = even_neighbour(f.abs(), &digits, exponent) {
+            digits = even;
+        }
+    }
+    let k = digits.len() as i32;
+    // The decimal point goes after the first `n` digits.
+    let n = exponent + 1;
+    let mut s = String::new();
+    if f < 0.0 {
+        s.push('-');
+    }
+    if k <= n && n <= 21 {
+        s.push_str(&digits);
+        s.extend((k..n).map(|_| '0'));
+    } else if 0 < n && n <= 21 {
+        s.push_str(&digits[..n as usize]);
+        s.push('.');
+        s.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        s.push_str("0.");
+        s.extend((n..0).map(|_| '0'));
+        s.push_str(&digits);
+    } else {
+        s.push_str(&digits[..1]);
+        if k > 1 {
+            s.push('.');
+            s.push_str(&digits[1..]);
+        }
+        s.push('e');
+        s.push(if n > 0 { '+' } else { '-' });
+        s.push_str(&(n - 1).abs().to_string());
+    }
+    Ok(s)
+}
+
+/// When `f` is exactly halfway between `digits` and a neighbour that also
+/// reads back as `f`, the neighbour, whose last digit is even. ECMAScript
+/// picks the even one of two equally close candidates where Rust need not.
+fn even_neighbour(f: f64, digits: &str, exponent: i32) -> Option<String> {
+    let k = digits.len();
+    // Every double has fewer than 800 significant digits.
+    let exact = format!("{:.800e}", f);
+    let e = exact.find('e').unwrap();
+    if exact[e + 1..].parse::<i32>().ok()? != exponent {
+        return None;
+    }
+    let exact: String = exact[..e].chars().filter(|&c| c != '.').collect();
+    if exact.as_bytes()[k] != b'5' || exact[k + 1..].bytes().any(|b| b != b'0') {
+        return None;
+    }
+    let lower = &exact[..k];
+    let neighbour = if lower != digits {
+        lower.to_string()
+    } else if !digits.ends_with('9') {
+        let last = digits.as_bytes()[k - 1] + 1;
+        format!("{}{}", &digits[..k - 1], last as char)
+    } else {
+        return None;
+    };
+    let text = format!("{}.{}e{}", &neighbour[..1], &neighbour[1..], exponent);
+    if text.parse::<f64>().ok()? == f {
+        Some(neighbour)
+    } else {
+        None
+    }
+}
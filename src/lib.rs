@@ -1,35 +1,327 @@
 #[macro_use]
 extern crate error_chain;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+#[cfg(feature = "derive")]
+extern crate jsonpull_derive;
+#[cfg(feature = "regex")]
+extern crate regex;
 
-use std::io::BufRead;
+use std::cmp;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{BufRead, Seek, SeekFrom, Write};
+use std::mem;
+use std::str;
+use std::sync::Arc;
+#[cfg(feature = "mmap")]
+use std::io::Cursor;
+#[cfg(feature = "mmap")]
+use std::path::Path;
 
 pub mod errors;
 pub use errors::*;
+pub mod base64;
+pub mod csv;
+pub mod diff;
+pub use diff::diff;
+pub mod encoding;
+pub mod entries;
+pub mod index;
+pub mod intern;
+pub mod jcs;
+pub mod parallel;
+pub mod patch;
+pub mod path;
+pub mod pull;
+pub use pull::Pull;
+pub mod scan;
+pub mod schema;
+pub mod transform;
+mod typed;
+pub mod validate;
+pub mod value;
+pub mod writer;
 
-#[derive(Debug, PartialEq)]
+use encoding::Decoder;
+use intern::KeyInterner;
+use path::{Shape, Step};
+use scan::Scanner;
+use writer::JsonWriter;
+#[cfg(feature = "mmap")]
+pub use memmap2::Mmap;
+#[cfg(feature = "derive")]
+pub use jsonpull_derive::JsonPull;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Event {
     Start(Block),
     End(Block),
     Key(String),
+    /// A key shared with earlier occurrences of it, see
+    /// [`Parser::intern_keys`].
+    InternedKey(Arc<str>),
     String(String),
+    /// Part of a string value longer than the chunk size, see
+    /// [`Parser::string_chunk_size`]. Chunks are split between characters.
+    StringChunk(String),
+    /// Follows the last `StringChunk` of a string.
+    StringEnd,
+    /// A key that is not valid UTF-8, see [`StringPolicy::Raw`].
+    RawKey(Vec<u8>),
+    /// A string that is not valid UTF-8, see [`StringPolicy::Raw`].
+    Bytes(Vec<u8>),
+    Number(N),
+    Bool(bool),
+    Null,
+}
+
+/// An event borrowing string contents from the parser, see
+/// [`Parser::next_ref`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventRef<'a> {
+    Start(Block),
+    End(Block),
+    Key(&'a str),
+    String(&'a str),
+    StringChunk(&'a str),
+    StringEnd,
+    RawKey(&'a [u8]),
+    Bytes(&'a [u8]),
     Number(N),
     Bool(bool),
     Null,
 }
 
-#[derive(Debug, PartialEq)]
+impl Event {
+    /// The key of a `Key` or `InternedKey` event.
+    pub fn key(&self) -> Option<&str> {
+        match *self {
+            Event::Key(ref s) => Some(s),
+            Event::InternedKey(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn to_ref(&self) -> EventRef<'_> {
+        match *self {
+            Event::Start(b) => EventRef::Start(b),
+            Event::End(b) => EventRef::End(b),
+            Event::Key(ref s) => EventRef::Key(s),
+            Event::InternedKey(ref s) => EventRef::Key(s),
+            Event::String(ref s) => EventRef::String(s),
+            Event::StringChunk(ref s) => EventRef::StringChunk(s),
+            Event::StringEnd => EventRef::StringEnd,
+            Event::RawKey(ref b) => EventRef::RawKey(b),
+            Event::Bytes(ref b) => EventRef::Bytes(b),
+            Event::Number(n) => EventRef::Number(n),
+            Event::Bool(b) => EventRef::Bool(b),
+            Event::Null => EventRef::Null,
+        }
+    }
+}
+
+impl<'a> EventRef<'a> {
+    pub fn into_owned(self) -> Event {
+        match self {
+            EventRef::Start(b) => Event::Start(b),
+            EventRef::End(b) => Event::End(b),
+            EventRef::Key(s) => Event::Key(s.to_string()),
+            EventRef::String(s) => Event::String(s.to_string()),
+            EventRef::StringChunk(s) => Event::StringChunk(s.to_string()),
+            EventRef::StringEnd => Event::StringEnd,
+            EventRef::RawKey(b) => Event::RawKey(b.to_vec()),
+            EventRef::Bytes(b) => Event::Bytes(b.to_vec()),
+            EventRef::Number(n) => Event::Number(n),
+            EventRef::Bool(b) => Event::Bool(b),
+            EventRef::Null => Event::Null,
+        }
+    }
+}
+
+/// Type of the next event, see [`Parser::peek_kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Object,
+    Array,
+    /// The end of an object, array or chunked string.
+    End,
+    Key,
+    String,
+    Number,
+    Bool,
+    Null,
+}
+
+impl Event {
+    pub fn kind(&self) -> Kind {
+        match *self {
+            Event::Start(Block::Object) => Kind::Object,
+            Event::Start(Block::Array) => Kind::Array,
+            Event::End(_) | Event::StringEnd => Kind::End,
+            Event::Key(_) | Event::InternedKey(_) | Event::RawKey(_) => Kind::Key,
+            Event::String(_) | Event::StringChunk(_) | Event::Bytes(_) => Kind::String,
+            Event::Number(_) => Kind::Number,
+            Event::Bool(_) => Kind::Bool,
+            Event::Null => Kind::Null,
+        }
+    }
+}
+
+/// An event as first read by the parser. The contents of `Str` are in
+/// `Parser::scratch`.
+enum Raw {
+    Event(Event),
+    Str(StrKind),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum StrKind {
+    Key,
+    Value,
+    Chunk,
+}
+
+/// Where the parser is in a string read in chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkState {
+    None,
+    InString,
+    /// The last chunk has been returned, `StringEnd` is next.
+    EndPending,
+}
+
+/// Chunk size used by `Parser::read_string_to` when none is configured.
+const READ_TO_CHUNK_SIZE: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum N {
     Float(f64),
     Int(i64),
     Uint(u64),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Block {
     Object,
     Array,
 }
 
+/// What to do with strings that are not valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringPolicy {
+    /// Fail with an error.
+    Strict,
+    /// Replace invalid sequences with U+FFFD.
+    Lossy,
+    /// Return such strings unchanged as `Event::Bytes` or `Event::RawKey`.
+    /// Valid strings are still returned as `Event::String` and `Event::Key`.
+    /// Chunks of a string split by `Parser::string_chunk_size` have no such
+    /// form and are an error, but `Parser::read_string_to` writes their
+    /// bytes unchanged.
+    Raw,
+}
+
+/// What to do with `\u` escapes of a surrogate that is not part of a pair,
+/// like `"\uD800"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurrogatePolicy {
+    /// Fail with an error.
+    Error,
+    /// Replace the escape with U+FFFD.
+    Replace,
+    /// Encode the surrogate as in [WTF-8](https://simonsapin.github.io/wtf-8/).
+    /// The result is not valid UTF-8, so this is meant to be combined with
+    /// `StringPolicy::Raw`.
+    Wtf8,
+}
+
+/// What to do when an object has the same key more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Return every key.
+    Allow,
+    /// Fail with `ErrorKind::DuplicateKey` at the second occurrence.
+    Error,
+    /// Return every key, and report the repeated ones as warnings and to
+    /// the callback set with `Parser::on_duplicate_key`.
+    Warn,
+}
+
+/// Something the parser tolerated, see `Parser::collect_warnings`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// Where the value, key or closing bracket concerned starts.
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A comma before a closing bracket, as in `[1, 2,]`.
+    TrailingComma,
+    /// A key repeated within an object, under `DuplicateKeys::Warn`.
+    DuplicateKey(String),
+    /// Invalid UTF-8 in a string replaced under `StringPolicy::Lossy`.
+    LossyUtf8,
+    /// A number, given as written, that is not exactly the closest `f64` to
+    /// it, because it has too many digits or is out of range.
+    PrecisionLoss(String),
+    /// A UTF-8 byte order mark at the start of the input.
+    BomSkipped,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            WarningKind::TrailingComma => f.write_str("trailing comma")?,
+            WarningKind::DuplicateKey(ref key) => write!(f, "duplicate key {:?}", key)?,
+            WarningKind::LossyUtf8 => f.write_str("invalid UTF-8 replaced")?,
+            WarningKind::PrecisionLoss(ref n) => write!(f, "{} cannot be represented exactly", n)?,
+            WarningKind::BomSkipped => f.write_str("byte order mark skipped")?,
+        }
+        write!(f, " at line {}, offset {}", self.position.line, self.position.offset)
+    }
+}
+
+/// A callback given to the parser.
+struct Callback<F: ?Sized>(Box<F>);
+
+impl<F: ?Sized> fmt::Debug for Callback<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Callback")
+    }
+}
+
+type DuplicateKeyFn = dyn FnMut(&str, Position) + Send;
+type WarningFn = dyn FnMut(&Warning) + Send;
+
+#[derive(Debug, Default)]
+struct Warnings {
+    collect: bool,
+    collected: Vec<Warning>,
+    callback: Option<Callback<WarningFn>>,
+}
+
+impl Warnings {
+    fn enabled(&self) -> bool {
+        self.collect || self.callback.is_some()
+    }
+
+    fn report(&mut self, kind: WarningKind, position: Position) {
+        if !self.enabled() {
+            return;
+        }
+        let warning = Warning { kind, position };
+        if let Some(ref mut f) = self.callback {
+            (f.0)(&warning);
+        }
+        if self.collect {
+            self.collected.push(warning);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 enum ContainerType {
     Object,
@@ -49,13 +341,21 @@ pub enum ExpectType {
 pub struct Container {
     t: ContainerType,
     expect: ExpectType,
+    /// Number of values read in the container so far.
+    values: usize,
 }
 
 impl Container {
+    fn value_done(&mut self) {
+        self.expect = ExpectType::Comma;
+        self.values += 1;
+    }
+
     fn object() -> Container {
         Container {
             t: ContainerType::Object,
             expect: ExpectType::Key,
+            values: 0,
         }
     }
 
@@ -63,6 +363,7 @@ impl Container {
         Container {
             t: ContainerType::Array,
             expect: ExpectType::Value,
+            values: 0,
         }
     }
 
@@ -70,23 +371,46 @@ impl Container {
         Container {
             t: ContainerType::Root,
             expect: ExpectType::Value,
+            values: 0,
         }
     }
 }
 
+/// Location in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// Byte offset from the start of the input.
+    pub offset: u64,
+    /// 1-based line number.
+    pub line: usize,
+}
+
 #[derive(Debug)]
 struct JsonReader<B: BufRead> {
     r: B,
     tmp: Option<u8>,
     line: usize,
+    offset: u64,
+    scanner: Scanner,
 }
 
 impl<B: BufRead> JsonReader<B> {
     fn new(r: B) -> JsonReader<B> {
         JsonReader {
-            r: r,
+            r,
             tmp: None,
             line: 0,
+            offset: 0,
+            scanner: Scanner::new(),
+        }
+    }
+
+    /// Position of the next byte `next` will return.
+    fn position(&self) -> Position {
+        let pushed_back = self.tmp.is_some();
+        Position {
+            offset: self.offset - pushed_back as u64,
+            line: self.line + 1 - (self.tmp == Some(b'\n')) as usize,
         }
     }
 
@@ -95,11 +419,12 @@ impl<B: BufRead> JsonReader<B> {
             self.tmp = None;
             return Ok(Some(c));
         }
-        let mut b = [0];
-        if self.r.read(&mut b)? == 0 {
-            return Ok(None);
-        }
-        let c = b[0];
+        let c = match self.r.fill_buf()?.first() {
+            Some(&c) => c,
+            None => return Ok(None),
+        };
+        self.r.consume(1);
+        self.offset += 1;
         if c == b'\n' {
             self.line += 1;
         }
@@ -107,34 +432,84 @@ impl<B: BufRead> JsonReader<B> {
     }
 
     fn next_must(&mut self) -> Result<u8> {
-        self.next()?
-            .map(Ok)
-            .or(Some(Err("EOF".into())))
-            .unwrap()
-    }
-
-    fn push_back(&mut self, c: u8) {
-        assert!(self.tmp.is_none());
-        self.tmp = Some(c);
+        match self.next()? {
+            Some(c) => Ok(c),
+            None => Err("EOF".into()),
+        }
     }
 
-    fn take_while<F>(&mut self, buf: &mut Vec<u8>, f: F) -> Result<usize>
-        where F: Fn(u8) -> bool
+    /// Consumes the longest prefix of the input accepted by `run`, but no
+    /// more than `max` bytes, appending it to `buf` if given. Returns whether
+    /// input is left after the prefix, i.e. `false` at EOF.
+    fn consume_run<F>(&mut self, mut buf: Option<&mut Vec<u8>>, max: usize, run: F) -> Result<bool>
+        where F: Fn(&Scanner, &[u8]) -> usize
     {
-        let mut n = 0;
+        let mut left = max;
+        if let Some(c) = self.tmp {
+            if left == 0 || run(&self.scanner, &[c]) == 0 {
+                return Ok(true);
+            }
+            self.tmp = None;
+            left -= 1;
+            if let Some(ref mut buf) = buf {
+                buf.push(c);
+            }
+        }
         loop {
-            if let Some(c) = self.next()? {
-                if f(c) {
-                    buf.push(c);
-                    n += 1;
-                } else {
-                    self.push_back(c);
-                    return Ok(n);
+            let (n, more) = {
+                let data = self.r.fill_buf()?;
+                if data.is_empty() {
+                    return Ok(false);
                 }
-            } else {
-                return Err("EOF".into());
+                let n = cmp::min(run(&self.scanner, data), left);
+                let taken = &data[..n];
+                self.line += taken.iter().filter(|&&c| c == b'\n').count();
+                if let Some(ref mut buf) = buf {
+                    buf.extend_from_slice(taken);
+                }
+                (n, n < data.len())
+            };
+            self.r.consume(n);
+            self.offset += n as u64;
+            left -= n;
+            if more {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Skips a UTF-8 byte order mark at the current position, returning
+    /// whether there was one.
+    fn skip_bom(&mut self) -> Result<bool> {
+        match self.next()? {
+            Some(0xEF) if self.next_must()? != 0xBB || self.next_must()? != 0xBF => {
+                return Err("invalid byte order mark".into());
             }
+            Some(0xEF) => return Ok(true),
+            Some(c) => self.push_back(c),
+            None => (),
         }
+        Ok(false)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<()> {
+        self.consume_run(None, usize::MAX, Scanner::whitespace_run).map(|_| ())
+    }
+
+    /// Appends string content up to the next `"` or `\`, or until `buf`
+    /// holds `limit` bytes.
+    fn take_string_run(&mut self, buf: &mut Vec<u8>, limit: usize) -> Result<()> {
+        let max = limit.saturating_sub(buf.len());
+        if self.consume_run(Some(buf), max, Scanner::string_run)? {
+            Ok(())
+        } else {
+            Err("EOF".into())
+        }
+    }
+
+    fn push_back(&mut self, c: u8) {
+        assert!(self.tmp.is_none());
+        self.tmp = Some(c);
     }
 }
 
@@ -142,6 +517,27 @@ impl<B: BufRead> JsonReader<B> {
 pub struct Parser<B: BufRead> {
     reader: JsonReader<B>,
     containers: Vec<Container>,
+    event_offset: u64,
+    single_value: bool,
+    multiple_values: bool,
+    string_policy: StringPolicy,
+    surrogate_policy: SurrogatePolicy,
+    scratch: Vec<u8>,
+    lossy: String,
+    interner: Option<KeyInterner>,
+    chunk_size: Option<usize>,
+    chunk_state: ChunkState,
+    /// Start of a character cut off at the end of the last chunk.
+    carry: Vec<u8>,
+    /// The literal of the number being read.
+    number: String,
+    peeked: Option<Event>,
+    duplicate_keys: DuplicateKeys,
+    /// Keys read so far in each open object, unless duplicate keys are
+    /// allowed.
+    keys: Vec<HashSet<Vec<u8>>>,
+    on_duplicate_key: Option<Callback<DuplicateKeyFn>>,
+    warnings: Warnings,
 }
 
 impl<B: BufRead> Parser<B> {
@@ -149,17 +545,165 @@ impl<B: BufRead> Parser<B> {
         Parser {
             reader: JsonReader::new(reader),
             containers: vec![Container::root()],
+            event_offset: 0,
+            single_value: false,
+            multiple_values: false,
+            string_policy: StringPolicy::Strict,
+            surrogate_policy: SurrogatePolicy::Error,
+            scratch: Vec::new(),
+            lossy: String::new(),
+            interner: None,
+            chunk_size: None,
+            chunk_state: ChunkState::None,
+            carry: Vec::new(),
+            number: String::new(),
+            peeked: None,
+            duplicate_keys: DuplicateKeys::Allow,
+            keys: Vec::new(),
+            on_duplicate_key: None,
+            warnings: Warnings::default(),
         }
     }
 
+    /// Return string values longer than `size` bytes as a sequence of
+    /// `Event::StringChunk` followed by `Event::StringEnd`, so they never
+    /// have to be held in memory whole. Shorter strings are still returned
+    /// as `Event::String`, and keys are never split. Sizes below 4 are
+    /// rounded up so every chunk can hold a character.
+    ///
+    /// Chunks that are not valid UTF-8 are an error under
+    /// `StringPolicy::Strict` and `StringPolicy::Raw`, and replaced lossily
+    /// under `StringPolicy::Lossy`.
+    pub fn string_chunk_size(mut self, size: usize) -> Parser<B> {
+        self.chunk_size = Some(cmp::max(size, 4));
+        self
+    }
+
+    /// Return keys as `Event::InternedKey`, sharing one `Arc<str>` between
+    /// all occurrences of the same key, for up to `max_keys` distinct keys.
+    /// Keys seen after the table is full are returned as `Event::Key`.
+    pub fn intern_keys(mut self, max_keys: usize) -> Parser<B> {
+        self.interner = Some(KeyInterner::new(max_keys));
+        self
+    }
+
+    /// How to handle strings that are not valid UTF-8. Defaults to
+    /// `StringPolicy::Strict`.
+    pub fn string_policy(mut self, policy: StringPolicy) -> Parser<B> {
+        self.string_policy = policy;
+        self
+    }
+
+    /// How to handle escaped lone surrogates. Defaults to
+    /// `SurrogatePolicy::Error`.
+    pub fn surrogate_policy(mut self, policy: SurrogatePolicy) -> Parser<B> {
+        self.surrogate_policy = policy;
+        self
+    }
+
+    /// Use `scanner` to find the ends of strings and whitespace instead of
+    /// the fastest one available.
+    pub fn scanner(mut self, scanner: Scanner) -> Parser<B> {
+        self.reader.scanner = scanner;
+        self
+    }
+
+    /// What to do when an object has the same key more than once. Defaults
+    /// to `DuplicateKeys::Allow`; the other policies keep the keys of every
+    /// open object in memory.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Parser<B> {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Calls `f` with every key repeated within an object and where it
+    /// starts, and sets the policy to `DuplicateKeys::Warn`.
+    pub fn on_duplicate_key<F>(mut self, f: F) -> Parser<B>
+        where F: FnMut(&str, Position) + Send + 'static
+    {
+        self.duplicate_keys = DuplicateKeys::Warn;
+        self.on_duplicate_key = Some(Callback(Box::new(f)));
+        self
+    }
+
+    /// Keep a list of what the parser tolerated in the input, see
+    /// `WarningKind`, to be read with `warnings` or `take_warnings`.
+    pub fn collect_warnings(mut self, collect: bool) -> Parser<B> {
+        self.warnings.collect = collect;
+        self
+    }
+
+    /// Calls `f` with everything the parser tolerates in the input, as soon
+    /// as it has been read.
+    pub fn on_warning<F>(mut self, f: F) -> Parser<B>
+        where F: FnMut(&Warning) + Send + 'static
+    {
+        self.warnings.callback = Some(Callback(Box::new(f)));
+        self
+    }
+
+    /// The warnings collected so far, see `collect_warnings`.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings.collected
+    }
+
+    /// Returns and clears the warnings collected so far.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        mem::take(&mut self.warnings.collected)
+    }
+
+    /// Accept a sequence of top-level values, such as NDJSON or
+    /// concatenated JSON, instead of a single one.
+    pub fn multiple_values(mut self, multiple: bool) -> Parser<B> {
+        self.multiple_values = multiple;
+        self
+    }
+
+    /// Position of the next unread byte.
+    pub fn position(&self) -> Position {
+        self.reader.position()
+    }
+
+    /// Byte offset of the first byte of the last event returned by `next`.
+    ///
+    /// For `Key` this is the opening quote of the key, for every other event
+    /// it is where the value (or the closing bracket) starts.
+    pub fn event_offset(&self) -> u64 {
+        self.event_offset
+    }
+
+    /// Number of currently open objects and arrays.
+    pub fn depth(&self) -> usize {
+        self.containers.len() - 1
+    }
+
     #[inline]
     fn container(&mut self) -> &mut Container {
         self.containers.last_mut().unwrap()
     }
 
+    /// Reports a warning about the current event.
+    fn warn(&mut self, kind: WarningKind) {
+        let position = Position {
+            offset: self.event_offset,
+            line: self.reader.position().line,
+        };
+        self.warnings.report(kind, position);
+    }
+
+    fn skip_bom(&mut self) -> Result<()> {
+        if self.reader.skip_bom()? {
+            self.warn(WarningKind::BomSkipped);
+        }
+        Ok(())
+    }
+
     fn start_object(&mut self) -> Result<Event> {
         if let ExpectType::Value = self.container().expect {
             self.containers.push(Container::object());
+            if self.duplicate_keys != DuplicateKeys::Allow {
+                self.keys.push(HashSet::new());
+            }
             Ok(Event::Start(Block::Object))
         } else {
             Err(ErrorKind::Syntax(self.container().expect, '{').into())
@@ -168,8 +712,18 @@ impl<B: BufRead> Parser<B> {
 
     fn end_object(&mut self) -> Result<Event> {
         if let ContainerType::Object = self.container().t {
+            match (self.container().expect, self.container().values) {
+                (ExpectType::Key, 1..) => self.warn(WarningKind::TrailingComma),
+                (expect @ ExpectType::Colon, _) | (expect @ ExpectType::Value, _) => {
+                    return Err(ErrorKind::Syntax(expect, '}').into());
+                }
+                _ => (),
+            }
             let _ = self.containers.pop();
-            self.container().expect = ExpectType::Comma;
+            if self.duplicate_keys != DuplicateKeys::Allow {
+                let _ = self.keys.pop();
+            }
+            self.container().value_done();
             Ok(Event::End(Block::Object))
         } else {
             Err(ErrorKind::Syntax(self.container().expect, '}').into())
@@ -187,8 +741,11 @@ impl<B: BufRead> Parser<B> {
 
     fn end_array(&mut self) -> Result<Event> {
         if let ContainerType::Array = self.container().t {
+            if let (ExpectType::Value, 1..) = (self.container().expect, self.container().values) {
+                self.warn(WarningKind::TrailingComma);
+            }
             let _ = self.containers.pop();
-            self.container().expect = ExpectType::Comma;
+            self.container().value_done();
             Ok(Event::End(Block::Array))
         } else {
             Err(ErrorKind::Syntax(self.container().expect, ']').into())
@@ -209,7 +766,7 @@ impl<B: BufRead> Parser<B> {
         Ok(true)
     }
 
-    fn read_true(&mut self) -> Result<Event> {
+    fn parse_true(&mut self) -> Result<Event> {
         if let ExpectType::Value = self.container().expect {
             if self.expect(b"true")? {
                 Ok(Event::Bool(true))
@@ -221,7 +778,7 @@ impl<B: BufRead> Parser<B> {
         }
     }
 
-    fn read_false(&mut self) -> Result<Event> {
+    fn parse_false(&mut self) -> Result<Event> {
         if let ExpectType::Value = self.container().expect {
             if self.expect(b"false")? {
                 Ok(Event::Bool(false))
@@ -233,7 +790,7 @@ impl<B: BufRead> Parser<B> {
         }
     }
 
-    fn read_null(&mut self) -> Result<Event> {
+    fn parse_null(&mut self) -> Result<Event> {
         if let ExpectType::Value = self.container().expect {
             if self.expect(b"null")? {
                 Ok(Event::Null)
@@ -265,48 +822,72 @@ impl<B: BufRead> Parser<B> {
         Ok(n)
     }
 
-    fn parse_string_unicode_tail(&mut self, head: u16, buf: &mut Vec<u8>) -> Result<()> {
-        match self.parse_hex_escape()? {
-            tail @ 0xDC00...0xDFFF => {
-                let n = (((head - 0xD800) as u32) << 10 | (tail - 0xDC00) as u32) + 0x1_0000;
-
-                match std::char::from_u32(n as u32) {
-                    Some(c) => buf.append(&mut c.to_string().into_bytes()),
-                    None => {
-                        return Err("".into());
-                    }
-                }
+    fn lone_surrogate(&self, n: u16, buf: &mut Vec<u8>) -> Result<()> {
+        match self.surrogate_policy {
+            SurrogatePolicy::Error => return Err(format!("lone surrogate: \\u{:04X}", n).into()),
+            SurrogatePolicy::Replace => push_char(buf, '\u{FFFD}'),
+            SurrogatePolicy::Wtf8 => {
+                buf.extend_from_slice(&[0xE0 | (n >> 12) as u8,
+                                        0x80 | (n >> 6 & 0x3F) as u8,
+                                        0x80 | (n & 0x3F) as u8])
             }
-            0xD800...0xDBFF => return Err("".into()),
-            _ => return Err("".into()),
         }
         Ok(())
     }
 
     fn parse_string_unicode(&mut self, buf: &mut Vec<u8>) -> Result<()> {
-        match self.parse_hex_escape()? {
-            0xDC00...0xDFFF => return Err("".into()),
-            head @ 0xD800...0xDBFF => {
-                if !self.expect(b"\\u")? {
-                    return Err("".into());
-                }
-                self.parse_string_unicode_tail(head, buf)?;
-            }
-            n => {
-                match std::char::from_u32(n as u32) {
-                    Some(c) => buf.append(&mut c.to_string().into_bytes()),
-                    None => {
-                        return Err("".into());
+        let mut head = self.parse_hex_escape()?;
+        loop {
+            match head {
+                0xD800..=0xDBFF => {
+                    // Only a `\u` escape of a low surrogate may follow.
+                    match self.reader.next_must()? {
+                        b'\\' => {
+                            match self.reader.next_must()? {
+                                b'u' => {
+                                    let tail = self.parse_hex_escape()?;
+                                    if let 0xDC00..=0xDFFF = tail {
+                                        let n = (((head - 0xD800) as u32) << 10 |
+                                                 (tail - 0xDC00) as u32) +
+                                                0x1_0000;
+                                        let c = std::char::from_u32(n).unwrap();
+                                        push_char(buf, c);
+                                        return Ok(());
+                                    }
+                                    self.lone_surrogate(head, buf)?;
+                                    head = tail;
+                                }
+                                c => {
+                                    self.lone_surrogate(head, buf)?;
+                                    return self.parse_escaped(c, buf);
+                                }
+                            }
+                        }
+                        c => {
+                            self.reader.push_back(c);
+                            return self.lone_surrogate(head, buf);
+                        }
                     }
                 }
+                0xDC00..=0xDFFF => return self.lone_surrogate(head, buf),
+                n => {
+                    let c = std::char::from_u32(n as u32).unwrap();
+                    push_char(buf, c);
+                    return Ok(());
+                }
             }
         }
-        Ok(())
     }
 
     fn parse_string_escape(&mut self, buf: &mut Vec<u8>) -> Result<()> {
-        match self.reader.next_must()? {
-            c @ b'"' | c @ b'\\' | c @ b'/' => buf.push(c),
+        let c = self.reader.next_must()?;
+        self.parse_escaped(c, buf)
+    }
+
+    /// Handles the escape sequence starting with `c`, right after a `\`.
+    fn parse_escaped(&mut self, c: u8, buf: &mut Vec<u8>) -> Result<()> {
+        match c {
+            b'"' | b'\\' | b'/' => buf.push(c),
             b'b' => buf.push(b'\x08'),
             b'f' => buf.push(b'\x0c'),
             b'n' => buf.push(b'\n'),
@@ -319,49 +900,433 @@ impl<B: BufRead> Parser<B> {
         Ok(())
     }
 
-    fn parse_string(&mut self, buf: &mut Vec<u8>) -> Result<()> {
+    /// Parses string contents into `buf` until the closing quote, or until
+    /// `buf` holds at least `limit` bytes. Returns whether the string ended.
+    fn parse_string(&mut self, buf: &mut Vec<u8>, limit: usize) -> Result<bool> {
         loop {
-            let _n = self.reader
-                .take_while(buf, |c| match c {
-                    b'\\' | b'"' => false,
-                    _ => true,
-                })?;
+            self.reader.take_string_run(buf, limit)?;
+            if buf.len() >= limit {
+                return Ok(false);
+            }
             match self.reader
                       .next()?
                       .unwrap() {
                 b'\\' => self.parse_string_escape(buf)?,
-                b'"' => break,
+                b'"' => return Ok(true),
                 _ => unreachable!(),
             }
         }
-        Ok(())
     }
 
-    fn read_string(&mut self) -> Result<Event> {
+    fn start_string(&mut self) -> Result<Raw> {
         match self.container().expect {
             ExpectType::Key | ExpectType::Value => {
-                let mut buf = Vec::new();
-                self.parse_string(&mut buf)?;
-                let s = String::from_utf8(buf)?;
-                if let ExpectType::Key = self.container().expect {
+                let key = matches!(self.container().expect, ExpectType::Key);
+                // A byte past the chunk size tells whether the string is
+                // longer than that.
+                let limit = match self.chunk_size {
+                    Some(n) if !key => n + 1,
+                    _ => usize::MAX,
+                };
+                let mut buf = mem::take(&mut self.scratch);
+                buf.clear();
+                let r = self.parse_string(&mut buf, limit);
+                self.scratch = buf;
+                if !r? {
+                    self.chunk_state = ChunkState::InString;
+                    let rest = self.scratch.split_off(limit - 1);
+                    self.carry_incomplete_char();
+                    self.carry.extend_from_slice(&rest);
+                    return Ok(Raw::Str(StrKind::Chunk));
+                }
+                if key {
                     self.container().expect = ExpectType::Colon;
-                    Ok(Event::Key(s))
+                    self.check_duplicate_key()?;
+                    Ok(Raw::Str(StrKind::Key))
                 } else {
-                    self.container().expect = ExpectType::Comma;
-                    Ok(Event::String(s))
+                    self.container().value_done();
+                    Ok(Raw::Str(StrKind::Value))
+                }
+            }
+            _ => Err(ErrorKind::Syntax(self.container().expect, '"').into()),
+        }
+    }
+
+    /// Applies the duplicate key policy to the key just read into `scratch`.
+    fn check_duplicate_key(&mut self) -> Result<()> {
+        if self.duplicate_keys == DuplicateKeys::Allow {
+            return Ok(());
+        }
+        let keys = match self.keys.last_mut() {
+            Some(keys) => keys,
+            None => return Ok(()),
+        };
+        if !keys.contains(&self.scratch) {
+            keys.insert(self.scratch.clone());
+            return Ok(());
+        }
+        let at = Position {
+            offset: self.event_offset,
+            line: self.reader.position().line,
+        };
+        let key = String::from_utf8_lossy(&self.scratch);
+        match self.duplicate_keys {
+            DuplicateKeys::Error => Err(ErrorKind::DuplicateKey(key.into_owned(), at).into()),
+            _ => {
+                if let Some(ref mut f) = self.on_duplicate_key {
+                    (f.0)(&key, at);
+                }
+                let key = key.into_owned();
+                self.warn(WarningKind::DuplicateKey(key));
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads the next chunk of a string split up because of `chunk_size`.
+    fn continue_string(&mut self) -> Result<Raw> {
+        if let ChunkState::EndPending = self.chunk_state {
+            self.chunk_state = ChunkState::None;
+            self.container().value_done();
+            return Ok(Raw::Event(Event::StringEnd));
+        }
+        let mut buf = mem::take(&mut self.scratch);
+        buf.clear();
+        buf.append(&mut self.carry);
+        let r = self.parse_string(&mut buf, self.chunk_size.unwrap_or(usize::MAX));
+        self.scratch = buf;
+        if !r? {
+            self.carry_incomplete_char();
+            return Ok(Raw::Str(StrKind::Chunk));
+        }
+        if self.scratch.is_empty() {
+            self.chunk_state = ChunkState::EndPending;
+            return self.continue_string();
+        }
+        self.chunk_state = ChunkState::EndPending;
+        Ok(Raw::Str(StrKind::Chunk))
+    }
+
+    /// Moves a UTF-8 sequence cut off at the end of `scratch` to `carry`, so
+    /// that chunks are split between characters.
+    fn carry_incomplete_char(&mut self) {
+        let len = self.scratch.len();
+        for back in 1..cmp::min(4, len + 1) {
+            let c = self.scratch[len - back];
+            if c & 0xC0 == 0x80 {
+                // Continuation byte, keep looking for the start.
+                continue;
+            }
+            let needed = match c {
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF7 => 4,
+                _ => 1,
+            };
+            if needed > back {
+                self.carry = self.scratch.split_off(len - back);
+            }
+            return;
+        }
+    }
+
+    /// Turns `raw` into an owned event, taking the string out of `scratch`.
+    fn owned_event(&mut self, raw: Raw) -> Result<Event> {
+        let kind = match raw {
+            Raw::Event(ev) => return Ok(ev),
+            Raw::Str(kind) => kind,
+        };
+        if kind == StrKind::Key {
+            if let Some(ref mut interner) = self.interner {
+                if let Ok(k) = str::from_utf8(&self.scratch) {
+                    if let Some(k) = interner.intern(k) {
+                        return Ok(Event::InternedKey(k));
+                    }
+                }
+            }
+        }
+        let buf = mem::take(&mut self.scratch);
+        let s = match String::from_utf8(buf) {
+            Ok(s) => s,
+            Err(e) => {
+                match (self.string_policy, kind) {
+                    (StringPolicy::Strict, _) | (StringPolicy::Raw, StrKind::Chunk) => {
+                        return Err(e.into())
+                    }
+                    (StringPolicy::Raw, StrKind::Key) => return Ok(Event::RawKey(e.into_bytes())),
+                    (StringPolicy::Raw, StrKind::Value) => return Ok(Event::Bytes(e.into_bytes())),
+                    _ => {
+                        self.warn(WarningKind::LossyUtf8);
+                        String::from_utf8_lossy(e.as_bytes()).into_owned()
+                    }
                 }
             }
-            _ => return Err(ErrorKind::Syntax(self.container().expect, '"').into()),
+        };
+        Ok(match kind {
+            StrKind::Key => Event::Key(s),
+            StrKind::Value => Event::String(s),
+            StrKind::Chunk => Event::StringChunk(s),
+        })
+    }
+
+    /// Returns the next event without consuming it, so the following call
+    /// to `next` returns it again.
+    ///
+    /// The event is parsed in full, so `event_offset`, `position` and
+    /// `depth` already reflect it. See `peek_kind` for a cheaper check.
+    pub fn peek(&mut self) -> Result<Option<&Event>> {
+        if self.peeked.is_none() {
+            match self.next() {
+                None => return Ok(None),
+                Some(r) => self.peeked = Some(r?),
+            }
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    /// Type of the next event. Unlike `peek`, this only looks at the first
+    /// byte of the event for everything but the continuation of a chunked
+    /// string, so no string or number is parsed.
+    pub fn peek_kind(&mut self) -> Result<Option<Kind>> {
+        if self.peeked.is_none() && self.chunk_state == ChunkState::None {
+            let kind = match self.skip_separators()? {
+                Some(b'{') => Some(Kind::Object),
+                Some(b'[') => Some(Kind::Array),
+                Some(b'}') | Some(b']') => Some(Kind::End),
+                Some(b'"') if matches!(self.container().expect, ExpectType::Key) => Some(Kind::Key),
+                Some(b'"') => Some(Kind::String),
+                Some(b'-') | Some(b'0'..=b'9') => Some(Kind::Number),
+                Some(b't') | Some(b'f') => Some(Kind::Bool),
+                Some(b'n') => Some(Kind::Null),
+                _ => None,
+            };
+            if kind.is_some() {
+                return Ok(kind);
+            }
+        }
+        Ok(self.peek()?.map(Event::kind))
+    }
+
+    /// Consumes the whitespace, colons and commas before the next event the
+    /// way `next_raw` would, and returns its first byte without consuming
+    /// it. Anything out of place is left for `next_raw` to report.
+    fn skip_separators(&mut self) -> Result<Option<u8>> {
+        if self.reader.offset == 0 {
+            self.skip_bom()?;
+        }
+        loop {
+            if self.containers.len() == 1 && self.single_value &&
+               matches!(self.container().expect, ExpectType::Comma) {
+                return Ok(None);
+            }
+            self.reader.skip_whitespace()?;
+            let c = match self.reader.next()? {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+            let container = self.container();
+            match (c, container.expect, container.t) {
+                (b':', ExpectType::Colon, _) => container.expect = ExpectType::Value,
+                (b',', ExpectType::Comma, ContainerType::Object) => {
+                    container.expect = ExpectType::Key
+                }
+                (b',', ExpectType::Comma, ContainerType::Array) => {
+                    container.expect = ExpectType::Value
+                }
+                _ => {
+                    self.reader.push_back(c);
+                    return Ok(Some(c));
+                }
+            }
+        }
+    }
+
+    /// Turns a peeked event back into what `next_raw` returned for it.
+    fn unpeek(&mut self, ev: Event) -> Raw {
+        let (kind, bytes) = match ev {
+            Event::Key(s) => (StrKind::Key, s.into_bytes()),
+            Event::InternedKey(s) => (StrKind::Key, s.as_bytes().to_vec()),
+            Event::RawKey(b) => (StrKind::Key, b),
+            Event::String(s) => (StrKind::Value, s.into_bytes()),
+            Event::Bytes(b) => (StrKind::Value, b),
+            Event::StringChunk(s) => (StrKind::Chunk, s.into_bytes()),
+            ev => return Raw::Event(ev),
+        };
+        self.scratch = bytes;
+        Raw::Str(kind)
+    }
+
+    /// Like `next`, but strings and keys borrow from a buffer that is reused
+    /// for every event instead of being allocated, so once the buffer has
+    /// grown to fit the longest string, parsing allocates nothing.
+    pub fn next_ref(&mut self) -> Result<Option<EventRef<'_>>> {
+        let kind = match self.next_raw() {
+            None => return Ok(None),
+            Some(Err(e)) => return Err(e),
+            Some(Ok(Raw::Str(kind))) => kind,
+            Some(Ok(Raw::Event(ev))) => {
+                return Ok(Some(match ev {
+                    Event::Start(b) => EventRef::Start(b),
+                    Event::End(b) => EventRef::End(b),
+                    Event::StringEnd => EventRef::StringEnd,
+                    Event::Number(n) => EventRef::Number(n),
+                    Event::Bool(b) => EventRef::Bool(b),
+                    Event::Null => EventRef::Null,
+                    _ => unreachable!(),
+                }));
+            }
+        };
+        let Parser { ref scratch, ref mut lossy, string_policy, ref reader, event_offset, .. } =
+            *self;
+        let warnings = &mut self.warnings;
+        let s = match str::from_utf8(scratch) {
+            Ok(s) => s,
+            Err(e) => {
+                match (string_policy, kind) {
+                    (StringPolicy::Strict, _) | (StringPolicy::Raw, StrKind::Chunk) => {
+                        return Err(e.into())
+                    }
+                    (StringPolicy::Raw, StrKind::Key) => return Ok(Some(EventRef::RawKey(scratch))),
+                    (StringPolicy::Raw, StrKind::Value) => {
+                        return Ok(Some(EventRef::Bytes(scratch)))
+                    }
+                    _ => {
+                        let position = Position {
+                            offset: event_offset,
+                            line: reader.position().line,
+                        };
+                        warnings.report(WarningKind::LossyUtf8, position);
+                        lossy.clear();
+                        for chunk in scratch.utf8_chunks() {
+                            lossy.push_str(chunk.valid());
+                            if !chunk.invalid().is_empty() {
+                                lossy.push('\u{FFFD}');
+                            }
+                        }
+                        lossy
+                    }
+                }
+            }
+        };
+        Ok(Some(match kind {
+            StrKind::Key => EventRef::Key(s),
+            StrKind::Value => EventRef::String(s),
+            StrKind::Chunk => EventRef::StringChunk(s),
+        }))
+    }
+
+    /// Reads the value that starts with `first`, the event just returned by
+    /// `next`, passing its events to `f` in order, `first` included.
+    pub fn read_value_with<F>(&mut self, first: Event, mut f: F) -> Result<()>
+        where F: FnMut(Event) -> Result<()>
+    {
+        let mut shape = Shape::new();
+        let mut depth = 0;
+        let mut ev = first;
+        loop {
+            match shape.step(&ev) {
+                Step::Open => depth += 1,
+                Step::Close if depth > 0 => depth -= 1,
+                Step::Key if depth > 0 => (),
+                Step::Scalar | Step::Inner => (),
+                _ => return Err(format!("expected a value, found {:?}", ev).into()),
+            }
+            f(ev)?;
+            if depth == 0 {
+                return Ok(());
+            }
+            ev = match self.next() {
+                Some(ev) => ev?,
+                None => return Err("EOF".into()),
+            };
+        }
+    }
+
+    /// Writes the value that starts with `first`, the event just returned by
+    /// `next`, to `w` as it is read.
+    pub fn copy_value_to<W: Write>(&mut self, first: Event, w: &mut JsonWriter<W>) -> Result<()> {
+        self.read_value_with(first, |ev| w.write_event(&ev))
+    }
+
+    /// Reads the next value, which must be a string, and writes its contents
+    /// to `w` while they are parsed instead of collecting them in memory.
+    /// Returns the number of bytes written.
+    ///
+    /// Invalid UTF-8 is handled according to the string policy.
+    pub fn read_string_to<W: Write>(&mut self, w: &mut W) -> Result<u64> {
+        let chunk_size = self.chunk_size;
+        if chunk_size.is_none() {
+            self.chunk_size = Some(READ_TO_CHUNK_SIZE);
+        }
+        let r = self.copy_string(w);
+        self.chunk_size = chunk_size;
+        r
+    }
+
+    /// Reads the next value, which must be a base64 encoded string, and
+    /// writes the decoded bytes to `w` while the string is parsed. Returns
+    /// the number of bytes written.
+    ///
+    /// See the [`base64`](base64/index.html) module for the accepted forms.
+    pub fn read_base64_to<W: Write>(&mut self, w: &mut W) -> Result<u64> {
+        let mut decoder = base64::DecodeWriter::new(w);
+        self.read_string_to(&mut decoder)?;
+        decoder.finish()?;
+        Ok(decoder.written())
+    }
+
+    fn copy_string<W: Write>(&mut self, w: &mut W) -> Result<u64> {
+        let mut written = 0;
+        loop {
+            let kind = match self.next_raw() {
+                Some(Ok(Raw::Str(StrKind::Key))) | None => return Err("expected a string".into()),
+                Some(Ok(Raw::Event(Event::StringEnd))) => return Ok(written),
+                Some(Ok(Raw::Event(_))) => return Err("expected a string".into()),
+                Some(Err(e)) => return Err(e),
+                Some(Ok(Raw::Str(kind))) => kind,
+            };
+            match str::from_utf8(&self.scratch) {
+                Ok(_) => {
+                    w.write_all(&self.scratch)?;
+                    written += self.scratch.len() as u64;
+                }
+                Err(_) if self.string_policy == StringPolicy::Raw => {
+                    w.write_all(&self.scratch)?;
+                    written += self.scratch.len() as u64;
+                }
+                Err(e) if self.string_policy == StringPolicy::Strict => return Err(e.into()),
+                Err(_) => {
+                    self.warn(WarningKind::LossyUtf8);
+                    let s = String::from_utf8_lossy(&self.scratch);
+                    w.write_all(s.as_bytes())?;
+                    written += s.len() as u64;
+                }
+            }
+            if kind == StrKind::Value {
+                return Ok(written);
+            }
         }
     }
 
     fn read_number(&mut self, minus: bool) -> Result<Event> {
+        // The literal, for numbers that are not exact integers.
+        let mut text = mem::take(&mut self.number);
+        text.clear();
+        if minus {
+            text.push('-');
+        }
         let c = self.reader.next_must()?;
         if c == b'0' {
             match self.reader.next()? {
                 Some(b'0'...b'9') => return Err("found superfluous leading zero".into()),
-                Some(b'.') => self.reader.push_back(b'.'),
-                None | _ => {
+                Some(b'.') => {
+                    text.push('0');
+                    self.reader.push_back(b'.');
+                }
+                next => {
+                    if let Some(c) = next {
+                        self.reader.push_back(c);
+                    }
                     if !minus {
                         return Ok(Event::Number(N::Uint(0)));
                     } else {
@@ -375,16 +1340,18 @@ impl<B: BufRead> Parser<B> {
         let mut decimal_places: Option<i32> = None;
         let mut e: Option<i32> = None;
         let mut e_minus = false;
-        let mut tmp: u64 = 0;
+        let mut tmp: Option<u64> = Some(0);
         while let Some(c) = self.reader.next()? {
             match c {
                 b'0'...b'9' => {
+                    text.push(c as char);
                     if let Some(ev) = e {
-                        e = Some(ev * 10_i32 + (c - b'0') as i32);
+                        e = Some(ev.saturating_mul(10).saturating_add((c - b'0') as i32));
                     } else {
-                        tmp = tmp * 10_u64 + (c - b'0') as u64;
+                        tmp = tmp.and_then(|n| n.checked_mul(10))
+                            .and_then(|n| n.checked_add((c - b'0') as u64));
                         if let Some(n) = decimal_places {
-                            decimal_places = Some(n + 1);
+                            decimal_places = Some(n.saturating_add(1));
                         }
                     }
                 }
@@ -400,6 +1367,7 @@ impl<B: BufRead> Parser<B> {
                         return Err("".into());
                     }
                     self.reader.push_back(nc);
+                    text.push('.');
                     decimal_places = Some(0);
                 }
                 b'e' | b'E' => {
@@ -407,10 +1375,14 @@ impl<B: BufRead> Parser<B> {
                         return Err("".into());
                     }
                     let nc = self.reader.next_must()?;
+                    text.push('e');
                     match nc {
                         b'0'...b'9' => self.reader.push_back(nc),
                         b'+' => (),
-                        b'-' => e_minus = true,
+                        b'-' => {
+                            text.push('-');
+                            e_minus = true;
+                        }
                         _ => return Err("".into()),
                     }
                     e = Some(0);
@@ -422,37 +1394,137 @@ impl<B: BufRead> Parser<B> {
             }
         }
 
-        let index = e.map(|e| if e_minus { -e } else { e }).unwrap_or(0) -
-                    decimal_places.unwrap_or(0);
-        let n = if index >= 0 {
-            let n = tmp * power_of_ten(index as usize);
-            if minus {
-                N::Int(-(n as i64))
-            } else {
-                N::Uint(n)
+        let index = e.map(|e| if e_minus { -e } else { e })
+            .unwrap_or(0)
+            .saturating_sub(decimal_places.unwrap_or(0));
+        // Integers that fit are exact, everything else is left to the
+        // correctly rounded float parser.
+        let exact = match tmp {
+            Some(0) if index >= 0 => Some(0),
+            Some(n) if index >= 0 => {
+                10_u64.checked_pow(index as u32).and_then(|p| n.checked_mul(p))
             }
-        } else {
-            let n = tmp as f64 / power_of_ten(-index as usize) as f64;
-            if minus { N::Float(-n) } else { N::Float(n) }
+            _ => None,
         };
+        let n = match exact {
+            Some(n) if !minus => N::Uint(n),
+            Some(n) if n <= i64::MAX as u64 + 1 => N::Int((n as i64).wrapping_neg()),
+            _ => {
+                let f = text.parse().map_err(|_| format!("invalid number {}", text))?;
+                if self.warnings.enabled() && !is_closest_f64(&text, f) {
+                    self.warn(WarningKind::PrecisionLoss(text.clone()));
+                }
+                N::Float(f)
+            }
+        };
+        self.number = text;
 
         Ok(Event::Number(n))
     }
 }
 
+impl<B: BufRead> Parser<Decoder<B>> {
+    /// Parses input in UTF-8, UTF-16 or UTF-32, detected from its first
+    /// bytes, see [`encoding::Decoder`].
+    pub fn from_reader_detect_encoding(reader: B) -> Result<Parser<Decoder<B>>> {
+        Ok(Parser::from_reader(Decoder::new(reader)?))
+    }
+}
+
+impl<B: BufRead + Seek> Parser<B> {
+    /// Seeks `reader` to `offset` and returns a parser for the single value
+    /// starting there, e.g. an offset taken from an [`index::Index`].
+    ///
+    /// The parser stops after that value instead of reading the rest of the
+    /// enclosing document. Offsets reported by it stay absolute.
+    pub fn from_reader_at(mut reader: B, offset: u64) -> Result<Parser<B>> {
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut parser = Parser::from_reader(reader);
+        parser.reader.offset = offset;
+        parser.single_value = true;
+        Ok(parser)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Parser<Cursor<Mmap>> {
+    /// Maps the file at `path` into memory and parses it in place.
+    ///
+    /// The whole file is visible to the parser as one buffer, so strings and
+    /// whitespace are scanned in bulk without copying the input first. The
+    /// file must not be modified while the parser is alive.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Parser<Cursor<Mmap>>> {
+        let file = ::std::fs::File::open(path)?;
+        // Safety: see above, modifying the file is the caller's problem as
+        // with any other memory map.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Parser::from_mmap(mmap))
+    }
+
+    pub fn from_mmap(mmap: Mmap) -> Parser<Cursor<Mmap>> {
+        Parser::from_reader(Cursor::new(mmap))
+    }
+}
+
 impl<B: BufRead> Iterator for Parser<B> {
     type Item = Result<Event>;
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(c) = match self.reader.next() {
-                  Ok(x) => x,
-                  Err(e) => return Some(Err(e)),
-              } {
+        self.next_raw().map(|r| r.and_then(|raw| self.owned_event(raw)))
+    }
+}
+
+impl<B: BufRead> Parser<B> {
+    /// Ends the events at the end of the input, which must not be inside a
+    /// value and, unless reading multiple values, must have held one.
+    fn end_of_input(&mut self) -> Option<Result<Raw>> {
+        if self.containers.len() == 1 && (self.container().values > 0 || self.multiple_values) {
+            return None;
+        }
+        // Report it once, the next call ends the iteration.
+        self.containers.truncate(1);
+        self.keys.clear();
+        self.container().value_done();
+        Some(Err("unexpected EOF".into()))
+    }
+
+    fn next_raw(&mut self) -> Option<Result<Raw>> {
+        if let Some(ev) = self.peeked.take() {
+            return Some(Ok(self.unpeek(ev)));
+        }
+        if self.chunk_state != ChunkState::None {
+            return Some(self.continue_string());
+        }
+        if self.reader.offset == 0 {
+            if let Err(e) = self.skip_bom() {
+                return Some(Err(e));
+            }
+        }
+        loop {
+            if self.containers.len() == 1 {
+                if let ExpectType::Comma = self.container().expect {
+                    if self.single_value {
+                        return None;
+                    }
+                    if self.multiple_values {
+                        self.container().expect = ExpectType::Value;
+                    }
+                }
+            }
+            if let Err(e) = self.reader.skip_whitespace() {
+                return Some(Err(e));
+            }
+            let c = match self.reader.next() {
+                Ok(Some(c)) => c,
+                Ok(None) => return self.end_of_input(),
+                Err(e) => return Some(Err(e)),
+            };
+            self.event_offset = self.reader.position().offset - 1;
             let r = match c {
-                b'{' => self.start_object(),
-                b'}' => self.end_object(),
-                b'[' => self.start_array(),
-                b']' => self.end_array(),
-                b'"' => self.read_string(),
+                b'{' => self.start_object().map(Raw::Event),
+                b'}' => self.end_object().map(Raw::Event),
+                b'[' => self.start_array().map(Raw::Event),
+                b']' => self.end_array().map(Raw::Event),
+                b'"' => self.start_string(),
                 b':' => {
                     if let ExpectType::Colon = self.container().expect {
                         self.container().expect = ExpectType::Value;
@@ -466,7 +1538,9 @@ impl<B: BufRead> Iterator for Parser<B> {
                         match self.container().t {
                             ContainerType::Object => self.container().expect = ExpectType::Key,
                             ContainerType::Array => self.container().expect = ExpectType::Value,
-                            ContainerType::Root => panic!(),
+                            ContainerType::Root => {
+                                return Some(Err(ErrorKind::Syntax(ExpectType::Value, ',').into()));
+                            }
                         }
                         continue;
                     } else {
@@ -481,18 +1555,21 @@ impl<B: BufRead> Iterator for Parser<B> {
                     if let ExpectType::Value = self.container().expect {
                         self.reader.push_back(c);
                         let r = match c {
-                            b't' => self.read_true(),
-                            b'f' => self.read_false(),
-                            b'n' => self.read_null(),
+                            b't' => self.parse_true(),
+                            b'f' => self.parse_false(),
+                            b'n' => self.parse_null(),
                             b'-' => {
                                 let _ = self.reader.next();
                                 self.read_number(true)
                             }
                             b'0'...b'9' => self.read_number(false),
-                            c => panic!(c),
+                            c => {
+                                let _ = self.reader.next();
+                                Err(ErrorKind::Syntax(ExpectType::Value, c as char).into())
+                            }
                         };
-                        self.container().expect = ExpectType::Comma;
-                        r
+                        self.container().value_done();
+                        r.map(Raw::Event)
                     } else {
                         Err(ErrorKind::Syntax(self.container().expect, c as char).into())
                     }
@@ -500,17 +1577,38 @@ impl<B: BufRead> Iterator for Parser<B> {
             };
             return Some(r);
         }
-        None
     }
 }
 
-fn power_of_ten(p: usize) -> u64 {
-    if p == 0 {
-        return 1;
+/// Whether `f` reads back as the number `text`, comparing the significant
+/// digits of `text` with the shortest ones that identify `f`.
+fn is_closest_f64(text: &str, f: f64) -> bool {
+    if !f.is_finite() {
+        return false;
     }
-    let mut r = 10;
-    for _ in 1..p {
-        r *= 10;
+    let (mantissa, exponent) = match text.find('e') {
+        Some(e) => (&text[..e], text[e + 1..].parse::<i64>().unwrap_or(i64::MAX)),
+        None => (text, 0),
+    };
+    let fraction = mantissa.find('.').map_or(0, |dot| mantissa.len() - dot - 1);
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    let significant = digits.trim_start_matches('0');
+    let trimmed = significant.trim_end_matches('0');
+    if trimmed.is_empty() {
+        return f == 0.0;
     }
-    r
+    let zeros = (significant.len() - trimmed.len()) as i64;
+    let exponent = exponent.saturating_sub(fraction as i64).saturating_add(zeros);
+    // Like `1.2345e-7`, without trailing zeros.
+    let shortest = format!("{:e}", f.abs());
+    let e = shortest.find('e').unwrap();
+    let shortest_digits: String = shortest[..e].chars().filter(|&c| c != '.').collect();
+    let shortest_exponent = shortest[e + 1..].parse::<i64>().unwrap() -
+                            (shortest_digits.len() as i64 - 1);
+    trimmed == shortest_digits && exponent == shortest_exponent
+}
+
+fn push_char(buf: &mut Vec<u8>, c: char) {
+    let mut tmp = [0; 4];
+    buf.extend_from_slice(c.encode_utf8(&mut tmp).as_bytes());
 }
@@ -0,0 +1,181 @@
+//! Parsing the records of an in-memory input on several threads.
+//!
+//! The input is first split at record boundaries without parsing it: at
+//! newlines for NDJSON, and at the top-level commas of an array found by a
+//! quick structural scan. The records are then parsed concurrently, each by
+//! its own [`Parser`], and the results are returned in input order.
+
+use std::cmp;
+use std::thread;
+
+use {Event, Parser, Result};
+
+/// One record of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record<'a> {
+    /// Byte offset of `bytes` in the input.
+    pub offset: u64,
+    pub bytes: &'a [u8],
+}
+
+impl<'a> Record<'a> {
+    /// A parser over the record. Event offsets are relative to the whole
+    /// input.
+    pub fn parser(&self) -> Parser<&'a [u8]> {
+        let mut parser = Parser::from_reader(self.bytes);
+        parser.reader.offset = self.offset;
+        parser
+    }
+}
+
+fn is_blank(bytes: &[u8]) -> bool {
+    bytes.iter().all(|&c| c == b' ' || c == b'\t' || c == b'\r' || c == b'\n')
+}
+
+/// Splits NDJSON into its lines, skipping blank ones.
+pub fn split_lines(input: &[u8]) -> Vec<Record<'_>> {
+    let mut records = Vec::new();
+    let mut offset = 0;
+    for line in input.split(|&c| c == b'\n') {
+        if !is_blank(line) {
+            records.push(Record {
+                offset: offset as u64,
+                bytes: line,
+            });
+        }
+        offset += line.len() + 1;
+    }
+    records
+}
+
+/// Splits a top-level array into its elements.
+///
+/// Only strings and brackets are looked at, so malformed elements are not
+/// detected until they are parsed.
+pub fn split_array(input: &[u8]) -> Result<Vec<Record<'_>>> {
+    let start = match input.iter().position(|&c| !is_blank(&[c])) {
+        Some(i) if input[i] == b'[' => i + 1,
+        Some(i) => return Err(format!("expected '[', found '{}'", input[i] as char).into()),
+        None => return Err("EOF".into()),
+    };
+    let mut records = Vec::new();
+    let mut element = start;
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut i = start;
+    while i < input.len() {
+        let c = input[i];
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == b'\\' {
+                escaped = true;
+            } else if c == b'"' {
+                in_string = false;
+            }
+        } else {
+            match c {
+                b'"' => in_string = true,
+                b'[' | b'{' => depth += 1,
+                b']' | b'}' if depth > 0 => depth -= 1,
+                b',' | b']' if depth == 0 => {
+                    let bytes = &input[element..i];
+                    if is_blank(bytes) {
+                        // `[]` and a trailing comma are accepted like
+                        // `Parser` does.
+                        if c == b',' {
+                            return Err("empty array element".into());
+                        }
+                    } else {
+                        records.push(Record {
+                            offset: element as u64,
+                            bytes,
+                        });
+                    }
+                    if c == b']' {
+                        if !is_blank(&input[i + 1..]) {
+                            return Err("trailing characters after array".into());
+                        }
+                        return Ok(records);
+                    }
+                    element = i + 1;
+                }
+                b'}' => return Err("expected ']', found '}'".into()),
+                _ => (),
+            }
+        }
+        i += 1;
+    }
+    Err("EOF".into())
+}
+
+/// Runs `f` on a parser for every record, using up to `threads` threads
+/// (`0` for one per CPU), and returns the results in record order. If `f`
+/// panics on a thread, the records that thread had are errors.
+pub fn map_records<'a, T, F>(records: &[Record<'a>], threads: usize, f: F) -> Vec<Result<T>>
+    where T: Send,
+          F: Fn(&mut Parser<&'a [u8]>) -> Result<T> + Sync
+{
+    let threads = match threads {
+        0 => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        n => n,
+    };
+    let chunks = split_by_size(records, threads);
+    if chunks.len() <= 1 {
+        return records.iter().map(|r| f(&mut r.parser())).collect();
+    }
+    let f = &f;
+    thread::scope(|s| {
+        let handles: Vec<_> = chunks.into_iter()
+            .map(|chunk| {
+                let h = s.spawn(move || {
+                    chunk.iter().map(|r| f(&mut r.parser())).collect::<Vec<_>>()
+                });
+                (chunk.len(), h)
+            })
+            .collect();
+        handles.into_iter()
+            .flat_map(|(len, h)| {
+                h.join().unwrap_or_else(|_| {
+                    (0..len).map(|_| Err("parser thread panicked".into())).collect()
+                })
+            })
+            .collect()
+    })
+}
+
+/// Splits `records` into at most `n` contiguous runs of similar byte size.
+fn split_by_size<'r, 'a>(records: &'r [Record<'a>], n: usize) -> Vec<&'r [Record<'a>]> {
+    let total: usize = records.iter().map(|r| r.bytes.len()).sum();
+    let target = cmp::max(total / cmp::max(n, 1), 1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut size = 0;
+    for (i, r) in records.iter().enumerate() {
+        size += r.bytes.len();
+        if size >= target && chunks.len() + 1 < n {
+            chunks.push(&records[start..i + 1]);
+            start = i + 1;
+            size = 0;
+        }
+    }
+    if start < records.len() {
+        chunks.push(&records[start..]);
+    }
+    chunks
+}
+
+fn collect_events(parser: &mut Parser<&[u8]>) -> Result<Vec<Event>> {
+    parser.collect()
+}
+
+/// Parses every line of NDJSON into its events.
+pub fn parse_ndjson(input: &[u8], threads: usize) -> Vec<Result<Vec<Event>>> {
+    map_records(&split_lines(input), threads, collect_events)
+}
+
+/// Parses every element of a top-level array into its events.
+pub fn parse_array(input: &[u8], threads: usize) -> Result<Vec<Result<Vec<Event>>>> {
+    Ok(map_records(&split_array(input)?, threads, collect_events))
+}
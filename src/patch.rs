@@ -0,0 +1,658 @@
+//! Applying a [JSON Patch](https://www.rfc-editor.org/rfc/rfc6902) or a
+//! [JSON Merge Patch](https://www.rfc-editor.org/rfc/rfc7396) while a
+//! document streams from a parser to a writer.
+//!
+//! Operations on unrelated paths are applied in a single pass: the values
+//! they replace, remove or test are the only ones read whole, and members
+//! added to an object are written at its end. Operations that cannot be
+//! applied independently of each other, because one path leads to another or
+//! because they insert or remove elements of the same array, and every
+//! `move` and `copy`, are applied in memory to the smallest container holding
+//! all of their paths, in the order of the patch:
+//!
+//! ```
+//! # use jsonpull::Parser;
+//! # use jsonpull::patch::Patch;
+//! # use jsonpull::writer::JsonWriter;
+//! let patch = Patch::parse(r#"[{"op": "replace", "path": "/a", "value": 2},
+//!                             {"op": "add", "path": "/c", "value": [3]}]"#).unwrap();
+//! let mut p = Parser::from_reader(&br#"{"a": 1, "b": true}"#[..]);
+//! let mut w = JsonWriter::new(Vec::new());
+//! patch.apply(&mut p, &mut w).unwrap();
+//! assert_eq!(w.into_inner(), br#"{"a":2,"b":true,"c":[3]}"#);
+//! ```
+//!
+//! A merge patch never depends on the order of the document, so
+//! [`merge_patch`] only holds the patch in memory.
+
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+
+use path::{escape_token, parse_pointer};
+use value::{read_value, Value};
+use writer::JsonWriter;
+use {Block, Event, Parser, Result};
+
+#[derive(Debug, Clone)]
+enum Op {
+    Add(Value),
+    Remove,
+    Replace(Value),
+    Move(Vec<String>),
+    Copy(Vec<String>),
+    Test(Value),
+}
+
+#[derive(Debug, Clone)]
+struct Operation {
+    op: Op,
+    path: Vec<String>,
+}
+
+impl Operation {
+    fn from(&self) -> Option<&[String]> {
+        match self.op {
+            Op::Move(ref from) | Op::Copy(ref from) => Some(from),
+            _ => None,
+        }
+    }
+
+    /// The paths the operation reads or writes, each with whether it
+    /// inserts or removes there, shifting the elements of an array after it.
+    fn regions(&self) -> Vec<(&[String], bool)> {
+        let shifts = !matches!(self.op, Op::Replace(_) | Op::Test(_));
+        let mut regions = vec![(&self.path[..], shifts)];
+        match self.op {
+            Op::Move(ref from) => regions.push((from, true)),
+            Op::Copy(ref from) => regions.push((from, false)),
+            _ => (),
+        }
+        regions
+    }
+
+    /// The same operation on the value at `root`, which its paths start with.
+    fn relative_to(&self, root: usize) -> Operation {
+        let op = match self.op {
+            Op::Move(ref from) => Op::Move(from[root..].to_vec()),
+            Op::Copy(ref from) => Op::Copy(from[root..].to_vec()),
+            ref op => op.clone(),
+        };
+        Operation {
+            op,
+            path: self.path[root..].to_vec(),
+        }
+    }
+}
+
+/// A JSON Patch, see the [module documentation](index.html).
+#[derive(Debug, Clone)]
+pub struct Patch {
+    operations: Vec<Operation>,
+}
+
+impl Patch {
+    /// Reads a patch from its JSON form, an array of operations.
+    pub fn new(patch: &Value) -> Result<Patch> {
+        let operations = patch.as_array().ok_or("a JSON Patch must be an array")?;
+        let mut parsed = Vec::new();
+        for (i, operation) in operations.iter().enumerate() {
+            let member = |name: &str| {
+                operation.get(name).ok_or_else(|| -> ::Error {
+                    format!("operation {} has no `{}`", i, name).into()
+                })
+            };
+            let pointer = |name: &str| -> Result<Vec<String>> {
+                let pointer = member(name)?
+                    .as_str()
+                    .ok_or_else(|| format!("`{}` of operation {} is not a string", name, i))?;
+                parse_pointer(pointer)
+            };
+            let op = match member("op")?.as_str() {
+                Some("add") => Op::Add(member("value")?.clone()),
+                Some("remove") => Op::Remove,
+                Some("replace") => Op::Replace(member("value")?.clone()),
+                Some("move") => Op::Move(pointer("from")?),
+                Some("copy") => Op::Copy(pointer("from")?),
+                Some("test") => Op::Test(member("value")?.clone()),
+                _ => return Err(format!("operation {} has an unknown `op`", i).into()),
+            };
+            parsed.push(Operation {
+                op,
+                path: pointer("path")?,
+            });
+        }
+        Ok(Patch { operations: parsed })
+    }
+
+    pub fn parse(json: &str) -> Result<Patch> {
+        Patch::new(&Value::parse(json)?)
+    }
+
+    /// Reads a value from `p` and writes it to `w` with the patch applied.
+    /// On error, what has been written so far is incomplete.
+    pub fn apply<B, W>(&self, p: &mut Parser<B>, w: &mut JsonWriter<W>) -> Result<()>
+        where B: BufRead,
+              W: Write
+    {
+        let plan = self.plan();
+        Applier { p, w }.value(Some(&plan), &mut String::new())
+    }
+
+    /// Applies the patch to a value in memory. On error, the operations
+    /// before the failing one have been applied.
+    pub fn apply_to(&self, doc: &mut Value) -> Result<()> {
+        for operation in &self.operations {
+            apply_operation(doc, operation)?;
+        }
+        Ok(())
+    }
+
+    /// Sorts the operations into those applied as the document streams past
+    /// and those applied to a buffered container.
+    fn plan(&self) -> Node {
+        let mut groups: Vec<Group> = self.operations
+            .iter()
+            .enumerate()
+            .map(|(i, operation)| {
+                let root = operation.from()
+                    .map(|from| common_prefix(parent(from), parent(&operation.path)).len());
+                Group {
+                    operations: vec![i],
+                    root,
+                }
+            })
+            .collect();
+        // Merge groups that touch related paths until none do.
+        'merge: loop {
+            for i in 0..groups.len() {
+                for j in i + 1..groups.len() {
+                    if groups[i].conflicts(&groups[j], &self.operations) {
+                        let other = groups.remove(j);
+                        let group = &mut groups[i];
+                        group.operations.extend(other.operations);
+                        group.operations.sort();
+                        let mut root = parent(&self.operations[group.operations[0]].path);
+                        for &k in &group.operations {
+                            let operation = &self.operations[k];
+                            root = common_prefix(root, parent(&operation.path));
+                            if let Some(from) = operation.from() {
+                                root = common_prefix(root, parent(from));
+                            }
+                        }
+                        group.root = Some(root.len());
+                        continue 'merge;
+                    }
+                }
+            }
+            break;
+        }
+        let mut plan = Node::default();
+        for group in groups {
+            match group.root {
+                Some(root) => {
+                    let first = &self.operations[group.operations[0]];
+                    let operations = group.operations
+                        .iter()
+                        .map(|&k| self.operations[k].relative_to(root))
+                        .collect();
+                    plan.insert(&first.path[..root], Action::Buffer(operations));
+                }
+                None => {
+                    let operation = &self.operations[group.operations[0]];
+                    let action = match operation.op {
+                        Op::Add(ref value) => Action::Add(value.clone()),
+                        Op::Remove => Action::Remove,
+                        Op::Replace(ref value) => Action::Replace(value.clone()),
+                        Op::Test(ref value) => Action::Test(value.clone()),
+                        Op::Move(_) | Op::Copy(_) => unreachable!(),
+                    };
+                    plan.insert(&operation.path, action);
+                }
+            }
+        }
+        plan
+    }
+}
+
+/// Operations applied together, either one as the document streams past,
+/// or several to the value at `root`, the number of leading tokens their
+/// paths share.
+#[derive(Debug)]
+struct Group {
+    operations: Vec<usize>,
+    root: Option<usize>,
+}
+
+impl Group {
+    fn regions<'o>(&self, operations: &'o [Operation]) -> Vec<(&'o [String], bool)> {
+        let first = &operations[self.operations[0]];
+        match self.root {
+            Some(root) => vec![(&first.path[..root], false)],
+            None => first.regions(),
+        }
+    }
+
+    fn conflicts(&self, other: &Group, operations: &[Operation]) -> bool {
+        let theirs = other.regions(operations);
+        self.regions(operations).iter().any(|&(a, a_shifts)| {
+            theirs.iter().any(|&(b, b_shifts)| {
+                let n = a.len().min(b.len());
+                a[..n] == b[..n] || a_shifts && shifts(a, b) || b_shifts && shifts(b, a)
+            })
+        })
+    }
+}
+
+/// Whether an insert or removal at `shifting` can move the array element
+/// that `other` is in or under.
+fn shifts(shifting: &[String], other: &[String]) -> bool {
+    let n = shifting.len();
+    n <= other.len() && parent(shifting) == &other[..n - 1] && array_like(&shifting[n - 1]) &&
+    array_like(&other[n - 1])
+}
+
+/// Whether a token could be an array index.
+fn array_like(token: &str) -> bool {
+    token == "-" || token.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn parent(path: &[String]) -> &[String] {
+    &path[..path.len().saturating_sub(1)]
+}
+
+fn common_prefix<'a>(a: &'a [String], b: &[String]) -> &'a [String] {
+    let n = a.iter().zip(b).take_while(|&(a, b)| a == b).count();
+    &a[..n]
+}
+
+#[derive(Debug)]
+enum Action {
+    Add(Value),
+    Remove,
+    Replace(Value),
+    Test(Value),
+    /// Read the value and apply these operations to it.
+    Buffer(Vec<Operation>),
+}
+
+/// What to do at a path and below it.
+#[derive(Debug, Default)]
+struct Node {
+    action: Option<Action>,
+    children: Vec<(String, Node)>,
+}
+
+impl Node {
+    fn insert(&mut self, path: &[String], action: Action) {
+        let mut node = self;
+        for token in path {
+            let i = match node.children.iter().position(|c| c.0 == *token) {
+                Some(i) => i,
+                None => {
+                    node.children.push((token.clone(), Node::default()));
+                    node.children.len() - 1
+                }
+            };
+            node = &mut node.children[i].1;
+        }
+        node.action = Some(action);
+    }
+
+    fn child(&self, token: &str) -> Option<usize> {
+        self.children.iter().position(|c| c.0 == token)
+    }
+}
+
+struct Applier<'a, 'w, B: BufRead + 'a, W: Write + 'w> {
+    p: &'a mut Parser<B>,
+    w: &'w mut JsonWriter<W>,
+}
+
+impl<'a, 'w, B: BufRead, W: Write> Applier<'a, 'w, B, W> {
+    fn next(&mut self) -> Result<Event> {
+        match self.p.next() {
+            Some(ev) => ev,
+            None => Err("EOF".into()),
+        }
+    }
+
+    /// Copies the next value with the changes of `node`. `path` is its
+    /// pointer, for errors.
+    fn value(&mut self, node: Option<&Node>, path: &mut String) -> Result<()> {
+        let ev = self.next()?;
+        let node = match node {
+            Some(node) => node,
+            None => return self.p.copy_value_to(ev, self.w),
+        };
+        match node.action {
+            Some(Action::Add(ref value)) | Some(Action::Replace(ref value)) => {
+                self.p.read_value_with(ev, |_| Ok(()))?;
+                value.write(self.w)
+            }
+            Some(Action::Test(ref value)) => {
+                let old = read_value(self.p, ev)?;
+                if old != *value {
+                    return Err(format!("test failed at {:?}", path).into());
+                }
+                old.write(self.w)
+            }
+            Some(Action::Buffer(ref operations)) => {
+                let mut doc = read_value(self.p, ev)?;
+                for operation in operations {
+                    apply_operation(&mut doc, operation)
+                        .map_err(|e| format!("{} (below {:?})", e, path))?;
+                }
+                doc.write(self.w)
+            }
+            Some(Action::Remove) => Err("cannot remove the whole document".into()),
+            None if node.children.is_empty() => self.p.copy_value_to(ev, self.w),
+            None => {
+                match ev {
+                    Event::Start(Block::Object) => self.object(node, path),
+                    Event::Start(Block::Array) => self.array(node, path),
+                    _ => {
+                        let missing = child_path(path, &node.children[0].0);
+                        Err(format!("no value at {:?}", missing).into())
+                    }
+                }
+            }
+        }
+    }
+
+    fn object(&mut self, node: &Node, path: &mut String) -> Result<()> {
+        self.w.write_event(&Event::Start(Block::Object))?;
+        let mut seen = vec![false; node.children.len()];
+        loop {
+            let ev = self.next()?;
+            let key = match ev {
+                Event::End(_) => break,
+                Event::RawKey(ref k) => node.child(&String::from_utf8_lossy(k)),
+                _ => node.child(ev.key().ok_or_else(|| format!("expected a key, found {:?}", ev))?),
+            };
+            let child = match key {
+                Some(i) => {
+                    seen[i] = true;
+                    &node.children[i]
+                }
+                None => {
+                    self.w.write_event(&ev)?;
+                    self.value(None, path)?;
+                    continue;
+                }
+            };
+            if let Some(Action::Remove) = child.1.action {
+                let ev = self.next()?;
+                self.p.read_value_with(ev, |_| Ok(()))?;
+                continue;
+            }
+            self.w.write_event(&ev)?;
+            let len = path.len();
+            path.push('/');
+            path.push_str(&escape_token(&child.0));
+            self.value(Some(&child.1), path)?;
+            path.truncate(len);
+        }
+        for ((key, child), _) in node.children.iter().zip(seen).filter(|c| !c.1) {
+            match child.action {
+                Some(Action::Add(ref value)) => {
+                    self.w.write_event(&Event::Key(key.clone()))?;
+                    value.write(self.w)?;
+                }
+                _ => return Err(format!("no value at {:?}", child_path(path, key)).into()),
+            }
+        }
+        self.w.write_event(&Event::End(Block::Object))
+    }
+
+    fn array(&mut self, node: &Node, path: &mut String) -> Result<()> {
+        self.w.write_event(&Event::Start(Block::Array))?;
+        let mut seen = vec![false; node.children.len()];
+        let mut index = 0;
+        loop {
+            if matches!(self.p.peek()?, Some(&Event::End(_))) {
+                self.next()?;
+                break;
+            }
+            let token = index.to_string();
+            index += 1;
+            let i = match node.child(&token) {
+                Some(i) => i,
+                None => {
+                    self.value(None, path)?;
+                    continue;
+                }
+            };
+            seen[i] = true;
+            let child = &node.children[i].1;
+            match child.action {
+                Some(Action::Add(ref value)) => {
+                    // Inserted before the element at its index.
+                    value.write(self.w)?;
+                    self.value(None, path)?;
+                }
+                Some(Action::Remove) => {
+                    let ev = self.next()?;
+                    self.p.read_value_with(ev, |_| Ok(()))?;
+                }
+                _ => {
+                    let len = path.len();
+                    path.push('/');
+                    path.push_str(&token);
+                    self.value(Some(child), path)?;
+                    path.truncate(len);
+                }
+            }
+        }
+        for ((token, child), _) in node.children.iter().zip(seen).filter(|c| !c.1) {
+            match child.action {
+                Some(Action::Add(ref value)) if *token == "-" || *token == index.to_string() => {
+                    value.write(self.w)?;
+                }
+                _ => return Err(format!("no value at {:?}", child_path(path, token)).into()),
+            }
+        }
+        self.w.write_event(&Event::End(Block::Array))
+    }
+}
+
+fn child_path(path: &str, token: &str) -> String {
+    format!("{}/{}", path, escape_token(token))
+}
+
+fn pointer(path: &[String]) -> String {
+    path.iter().map(|t| format!("/{}", escape_token(t))).collect()
+}
+
+/// An array index in a JSON Pointer, which has no leading zeros.
+fn index(token: &str) -> Option<usize> {
+    if token.len() > 1 && token.starts_with('0') {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn get_mut<'v>(doc: &'v mut Value, path: &[String]) -> Result<&'v mut Value> {
+    let mut value = doc;
+    for (i, token) in path.iter().enumerate() {
+        let next = match *value {
+            Value::Object(ref mut members) => {
+                members.iter_mut().rev().find(|m| m.0 == *token).map(|m| &mut m.1)
+            }
+            Value::Array(ref mut elements) => index(token).and_then(move |i| elements.get_mut(i)),
+            _ => None,
+        };
+        value = next.ok_or_else(|| format!("no value at {:?}", pointer(&path[..i + 1])))?;
+    }
+    Ok(value)
+}
+
+fn add(doc: &mut Value, path: &[String], value: Value) -> Result<()> {
+    let (token, parent) = match path.split_last() {
+        Some((token, parent)) => (token, get_mut(doc, parent)?),
+        None => {
+            *doc = value;
+            return Ok(());
+        }
+    };
+    match *parent {
+        Value::Object(ref mut members) => {
+            match members.iter_mut().rev().find(|m| m.0 == *token) {
+                Some(member) => member.1 = value,
+                None => members.push((token.clone(), value)),
+            }
+        }
+        Value::Array(ref mut elements) => {
+            match index(token) {
+                _ if token == "-" => elements.push(value),
+                Some(i) if i <= elements.len() => elements.insert(i, value),
+                _ => return Err(format!("cannot add at {:?}", pointer(path)).into()),
+            }
+        }
+        _ => return Err(format!("cannot add at {:?}", pointer(path)).into()),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut Value, path: &[String]) -> Result<Value> {
+    let (token, parent) = match path.split_last() {
+        Some((token, parent)) => (token, get_mut(doc, parent)?),
+        None => return Err("cannot remove the whole document".into()),
+    };
+    let removed = match *parent {
+        Value::Object(ref mut members) => {
+            let removed = members.iter().rposition(|m| m.0 == *token).map(|i| members.remove(i).1);
+            members.retain(|m| m.0 != *token);
+            removed
+        }
+        Value::Array(ref mut elements) => {
+            index(token).filter(|&i| i < elements.len()).map(|i| elements.remove(i))
+        }
+        _ => None,
+    };
+    removed.ok_or_else(|| format!("no value at {:?}", pointer(path)).into())
+}
+
+fn apply_operation(doc: &mut Value, operation: &Operation) -> Result<()> {
+    let path = &operation.path;
+    match operation.op {
+        Op::Add(ref value) => add(doc, path, value.clone()),
+        Op::Remove => remove(doc, path).map(|_| ()),
+        Op::Replace(ref value) => {
+            *get_mut(doc, path)? = value.clone();
+            Ok(())
+        }
+        Op::Move(ref from) => {
+            if path.len() > from.len() && path.starts_with(from) {
+                return Err(format!("cannot move {:?} into itself", pointer(from)).into());
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        Op::Copy(ref from) => {
+            let value = get_mut(doc, from)?.clone();
+            add(doc, path, value)
+        }
+        Op::Test(ref value) => {
+            if *get_mut(doc, path)? == *value {
+                Ok(())
+            } else {
+                Err(format!("test failed at {:?}", pointer(path)).into())
+            }
+        }
+    }
+}
+
+/// Reads a value from `p` and writes it to `w` with the merge patch `patch`
+/// applied.
+pub fn merge_patch<B: BufRead, W: Write>(p: &mut Parser<B>,
+                                         patch: &Value,
+                                         w: &mut JsonWriter<W>)
+                                         -> Result<()> {
+    let mut applier = Applier { p, w };
+    applier.merge(patch)
+}
+
+impl<'a, 'w, B: BufRead, W: Write> Applier<'a, 'w, B, W> {
+    fn merge(&mut self, patch: &Value) -> Result<()> {
+        let ev = self.next()?;
+        let members = match (patch, &ev) {
+            (Value::Object(members), &Event::Start(Block::Object)) => members,
+            _ => {
+                self.p.read_value_with(ev, |_| Ok(()))?;
+                let mut target = Value::Null;
+                merge(&mut target, patch);
+                return target.write(self.w);
+            }
+        };
+        self.w.write_event(&ev)?;
+        let mut seen = HashSet::new();
+        loop {
+            let ev = self.next()?;
+            let key = match ev.key() {
+                Some(k) => k.to_string(),
+                None if matches!(ev, Event::RawKey(_)) => {
+                    return Err("merge patches need keys in UTF-8".into())
+                }
+                None => break,
+            };
+            match patch.get(&key) {
+                Some(&Value::Null) => {
+                    let ev = self.next()?;
+                    self.p.read_value_with(ev, |_| Ok(()))?;
+                }
+                Some(value) => {
+                    self.w.write_event(&ev)?;
+                    self.merge(value)?;
+                }
+                None => {
+                    self.w.write_event(&ev)?;
+                    self.value(None, &mut String::new())?;
+                }
+            }
+            seen.insert(key);
+        }
+        for key in members.iter().map(|m| &m.0) {
+            if !seen.insert(key.clone()) {
+                continue;
+            }
+            let value = patch.get(key).unwrap();
+            if *value != Value::Null {
+                let mut target = Value::Null;
+                merge(&mut target, value);
+                self.w.write_event(&Event::Key(key.clone()))?;
+                target.write(self.w)?;
+            }
+        }
+        self.w.write_event(&Event::End(Block::Object))
+    }
+}
+
+/// Applies the merge patch `patch` to a value in memory.
+pub fn merge(target: &mut Value, patch: &Value) {
+    let members = match *patch {
+        Value::Object(ref members) => members,
+        _ => {
+            *target = patch.clone();
+            return;
+        }
+    };
+    if target.as_object().is_none() {
+        *target = Value::Object(Vec::new());
+    }
+    if let Value::Object(ref mut target) = *target {
+        for key in members.iter().map(|m| &m.0) {
+            let value = patch.get(key).unwrap();
+            let existing = target.iter().rposition(|m| m.0 == *key);
+            match (existing, value) {
+                (Some(_), &Value::Null) => target.retain(|m| m.0 != *key),
+                (None, &Value::Null) => (),
+                (Some(i), value) => merge(&mut target[i].1, value),
+                (None, value) => {
+                    let mut member = Value::Null;
+                    merge(&mut member, value);
+                    target.push((key.clone(), member));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Tracking the location of events inside a document as JSON Pointers
+//! ([RFC 6901](https://tools.ietf.org/html/rfc6901)).
+
+use std::fmt;
+
+use {Block, Event, EventRef, Kind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Segment::Key(ref k) => f.write_str(&escape_token(k)),
+            Segment::Index(i) => write!(f, "{}", i),
+        }
+    }
+}
+
+/// How an event relates to the values of a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Key,
+    Scalar,
+    /// Starts an object, array or chunked string.
+    Open,
+    /// Continues a chunked string.
+    Inner,
+    Close,
+}
+
+/// Tells which events begin and end values, across chunked strings.
+///
+/// Counting `Open` up and `Close` down gives the nesting of a value, which
+/// has ended when the count is back where it started.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Shape {
+    in_chunks: bool,
+}
+
+impl Shape {
+    pub fn new() -> Shape {
+        Shape::default()
+    }
+
+    pub fn step(&mut self, ev: &Event) -> Step {
+        self.step_kind(ev.kind(), matches!(*ev, Event::StringChunk(_)))
+    }
+
+    /// Like `step`, for events from `Parser::next_ref`.
+    pub fn step_ref(&mut self, ev: &EventRef) -> Step {
+        let kind = match *ev {
+            EventRef::Start(Block::Object) => Kind::Object,
+            EventRef::Start(Block::Array) => Kind::Array,
+            EventRef::End(_) | EventRef::StringEnd => Kind::End,
+            EventRef::Key(_) | EventRef::RawKey(_) => Kind::Key,
+            EventRef::String(_) | EventRef::StringChunk(_) | EventRef::Bytes(_) => Kind::String,
+            EventRef::Number(_) => Kind::Number,
+            EventRef::Bool(_) => Kind::Bool,
+            EventRef::Null => Kind::Null,
+        };
+        self.step_kind(kind, matches!(*ev, EventRef::StringChunk(_)))
+    }
+
+    fn step_kind(&mut self, kind: Kind, chunk: bool) -> Step {
+        match kind {
+            Kind::Object | Kind::Array => Step::Open,
+            Kind::End => {
+                self.in_chunks = false;
+                Step::Close
+            }
+            Kind::Key => Step::Key,
+            Kind::String if chunk && self.in_chunks => Step::Inner,
+            Kind::String if chunk => {
+                self.in_chunks = true;
+                Step::Open
+            }
+            _ => Step::Scalar,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Frame {
+    block: Block,
+    current: Option<Segment>,
+}
+
+/// Follows an event stream and keeps the path of the value it is in.
+///
+/// After `event` has been called, `pointer` returns the path of the value
+/// the event belongs to: the value itself for scalars and `Start`/`End`,
+/// the member for `Key`.
+#[derive(Debug, Clone, Default)]
+pub struct PathTracker {
+    frames: Vec<Frame>,
+    shape: Shape,
+}
+
+impl PathTracker {
+    pub fn new() -> PathTracker {
+        PathTracker::default()
+    }
+
+    /// Follows `ev`, returning how it relates to the values of the stream.
+    pub fn event(&mut self, ev: &Event) -> Step {
+        let step = self.shape.step(ev);
+        match *ev {
+            Event::Start(block) => {
+                self.advance();
+                self.frames.push(Frame {
+                    block,
+                    current: None,
+                });
+            }
+            Event::End(_) => {
+                let _ = self.frames.pop();
+            }
+            Event::Key(ref k) => self.key(k.clone()),
+            Event::InternedKey(ref k) => self.key(k.to_string()),
+            Event::RawKey(ref k) => self.key(String::from_utf8_lossy(k).into_owned()),
+            _ if step == Step::Scalar || step == Step::Open => self.advance(),
+            _ => (),
+        }
+        step
+    }
+
+    fn key(&mut self, k: String) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.current = Some(Segment::Key(k));
+        }
+    }
+
+    fn advance(&mut self) {
+        if let Some(frame) = self.frames.last_mut() {
+            if let Block::Array = frame.block {
+                let next = match frame.current {
+                    Some(Segment::Index(i)) => i + 1,
+                    _ => 0,
+                };
+                frame.current = Some(Segment::Index(next));
+            }
+        }
+    }
+
+    /// Number of segments in the current path, `0` at the root.
+    pub fn depth(&self) -> usize {
+        self.segments().count()
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = &Segment> {
+        self.frames.iter().filter_map(|f| f.current.as_ref())
+    }
+
+    pub fn pointer(&self) -> String {
+        let mut s = String::new();
+        for seg in self.segments() {
+            s.push('/');
+            s.push_str(&seg.to_string());
+        }
+        s
+    }
+}
+
+/// Escapes `~` and `/` in a reference token.
+pub fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Splits a JSON Pointer into its unescaped reference tokens.
+pub fn parse_pointer(pointer: &str) -> ::Result<Vec<String>> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(format!("invalid JSON pointer: {}", pointer).into());
+    }
+    Ok(pointer[1..]
+        .split('/')
+        .map(|t| t.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
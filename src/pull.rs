@@ -0,0 +1,111 @@
+//! Decoding Rust values directly from the event stream.
+//!
+//! [`Pull`] is implemented for the basic types, `Option`, `Vec` and `Box`,
+//! and can be derived for structs and enums with the `derive` feature:
+//!
+//! ```ignore
+//! #[derive(JsonPull)]
+//! struct Point {
+//!     x: f64,
+//!     y: f64,
+//!     #[jsonpull(rename = "label")]
+//!     name: Option<String>,
+//!     #[jsonpull(default)]
+//!     tags: Vec<String>,
+//! }
+//! ```
+//!
+//! Structs are read from objects, with unknown keys skipped and missing ones
+//! an error unless the field is an `Option` or marked `default`. Newtype
+//! structs are read like the value they wrap. Unit enum variants are read
+//! from their name as a string, other variants from an object with the name
+//! as its only key.
+
+use std::convert::TryFrom;
+use std::io::BufRead;
+
+use {Kind, Parser, Result};
+
+pub trait Pull: Sized {
+    /// Reads one value.
+    fn pull<B: BufRead>(parser: &mut Parser<B>) -> Result<Self>;
+
+    /// The value of a struct field whose key is missing.
+    fn missing(field: &'static str) -> Result<Self> {
+        Err(format!("missing field `{}`", field).into())
+    }
+}
+
+macro_rules! pull_int {
+    ($read:ident: $($t:ty),*) => {
+        $(
+            impl Pull for $t {
+                fn pull<B: BufRead>(parser: &mut Parser<B>) -> Result<$t> {
+                    let n = parser.$read()?;
+                    <$t>::try_from(n)
+                        .map_err(|_| format!("{} out of range for {}", n, stringify!($t)).into())
+                }
+            }
+        )*
+    };
+}
+
+pull_int!(read_i64: i8, i16, i32, i64, isize);
+pull_int!(read_u64: u8, u16, u32, u64, usize);
+
+impl Pull for f64 {
+    fn pull<B: BufRead>(parser: &mut Parser<B>) -> Result<f64> {
+        parser.read_f64()
+    }
+}
+
+impl Pull for f32 {
+    fn pull<B: BufRead>(parser: &mut Parser<B>) -> Result<f32> {
+        parser.read_f64().map(|n| n as f32)
+    }
+}
+
+impl Pull for bool {
+    fn pull<B: BufRead>(parser: &mut Parser<B>) -> Result<bool> {
+        parser.read_bool()
+    }
+}
+
+impl Pull for String {
+    fn pull<B: BufRead>(parser: &mut Parser<B>) -> Result<String> {
+        parser.read_string()
+    }
+}
+
+/// `null` or the value.
+impl<T: Pull> Pull for Option<T> {
+    fn pull<B: BufRead>(parser: &mut Parser<B>) -> Result<Option<T>> {
+        if parser.peek_kind()? == Some(Kind::Null) {
+            parser.read_null()?;
+            return Ok(None);
+        }
+        T::pull(parser).map(Some)
+    }
+
+    fn missing(_: &'static str) -> Result<Option<T>> {
+        Ok(None)
+    }
+}
+
+impl<T: Pull> Pull for Vec<T> {
+    fn pull<B: BufRead>(parser: &mut Parser<B>) -> Result<Vec<T>> {
+        parser.expect_array_start()?;
+        let mut v = Vec::new();
+        while parser.peek_kind()? != Some(Kind::End) {
+            v.push(T::pull(parser)?);
+        }
+        parser.expect_array_end()?;
+        Ok(v)
+    }
+}
+
+impl<T: Pull> Pull for Box<T> {
+    fn pull<B: BufRead>(parser: &mut Parser<B>) -> Result<Box<T>> {
+        T::pull(parser).map(Box::new)
+    }
+}
@@ -0,0 +1,297 @@
+//! Locating quotes, backslashes and whitespace in bulk.
+//!
+//! The reader uses a [`Scanner`] to skip over whitespace and the plain parts
+//! of strings without looking at one byte at a time. With the `simd` feature
+//! the input is classified in 64-byte blocks using SSE2 or AVX2 on x86 and
+//! NEON on AArch64, picked at runtime, with a scalar fallback everywhere
+//! else. Without it, the same runs are found by a plain byte loop.
+
+/// Bit `i` of each mask is set if byte `i` of a block is of that class.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Masks {
+    pub quote: u64,
+    pub backslash: u64,
+    /// Space, tab, carriage return and line feed.
+    pub whitespace: u64,
+}
+
+type Classify = fn(&[u8; 64]) -> Masks;
+
+#[derive(Clone, Copy)]
+pub struct Scanner {
+    name: &'static str,
+    classify: Classify,
+}
+
+impl ::std::fmt::Debug for Scanner {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_tuple("Scanner").field(&self.name).finish()
+    }
+}
+
+impl Default for Scanner {
+    fn default() -> Scanner {
+        Scanner::new()
+    }
+}
+
+impl Scanner {
+    /// The fastest implementation supported by the running CPU.
+    pub fn new() -> Scanner {
+        detect().unwrap_or_else(Scanner::scalar)
+    }
+
+    pub fn scalar() -> Scanner {
+        Scanner {
+            name: "scalar",
+            classify: classify_scalar,
+        }
+    }
+
+    /// Name of the implementation in use: `avx2`, `sse2`, `neon` or `scalar`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn classify(&self, block: &[u8; 64]) -> Masks {
+        (self.classify)(block)
+    }
+
+    /// Length of the prefix of `data` containing no `"` or `\`.
+    pub fn string_run(&self, data: &[u8]) -> usize {
+        self.run(data, |m| m.quote | m.backslash, |c| c == b'"' || c == b'\\')
+    }
+
+    /// Length of the whitespace prefix of `data`.
+    pub fn whitespace_run(&self, data: &[u8]) -> usize {
+        self.run(data, |m| !m.whitespace, |c| !is_whitespace(c))
+    }
+
+    #[cfg(feature = "simd")]
+    fn run<M, F>(&self, data: &[u8], stop_mask: M, stop: F) -> usize
+        where M: Fn(Masks) -> u64,
+              F: Fn(u8) -> bool
+    {
+        let mut i = 0;
+        while data.len() - i >= 64 {
+            let block = array_ref(&data[i..i + 64]);
+            let mask = stop_mask(self.classify(block));
+            if mask != 0 {
+                return i + mask.trailing_zeros() as usize;
+            }
+            i += 64;
+        }
+        i + data[i..].iter().position(|&c| stop(c)).unwrap_or(data.len() - i)
+    }
+
+    #[cfg(not(feature = "simd"))]
+    fn run<M, F>(&self, data: &[u8], _: M, stop: F) -> usize
+        where M: Fn(Masks) -> u64,
+              F: Fn(u8) -> bool
+    {
+        data.iter().position(|&c| stop(c)).unwrap_or(data.len())
+    }
+}
+
+#[cfg(feature = "simd")]
+fn array_ref(s: &[u8]) -> &[u8; 64] {
+    use std::convert::TryInto;
+    s.try_into().unwrap()
+}
+
+fn is_whitespace(c: u8) -> bool {
+    c == b' ' || c == b'\t' || c == b'\r' || c == b'\n'
+}
+
+fn classify_scalar(block: &[u8; 64]) -> Masks {
+    let mut m = Masks::default();
+    for (i, &c) in block.iter().enumerate() {
+        let bit = 1 << i;
+        match c {
+            b'"' => m.quote |= bit,
+            b'\\' => m.backslash |= bit,
+            b' ' | b'\t' | b'\r' | b'\n' => m.whitespace |= bit,
+            _ => (),
+        }
+    }
+    m
+}
+
+#[cfg(not(feature = "simd"))]
+fn detect() -> Option<Scanner> {
+    None
+}
+
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+fn detect() -> Option<Scanner> {
+    if is_x86_feature_detected!("avx2") {
+        Some(Scanner {
+            name: "avx2",
+            classify: x86::classify_avx2,
+        })
+    } else if is_x86_feature_detected!("sse2") {
+        Some(Scanner {
+            name: "sse2",
+            classify: x86::classify_sse2,
+        })
+    } else {
+        None
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+fn detect() -> Option<Scanner> {
+    if ::std::arch::is_aarch64_feature_detected!("neon") {
+        Some(Scanner {
+            name: "neon",
+            classify: neon::classify,
+        })
+    } else {
+        None
+    }
+}
+
+#[cfg(all(feature = "simd",
+          not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))))]
+fn detect() -> Option<Scanner> {
+    None
+}
+
+/// Every implementation usable on the running CPU, for differential testing.
+#[cfg_attr(not(feature = "simd"), allow(unused_mut))]
+pub fn available() -> Vec<Scanner> {
+    let mut v = vec![Scanner::scalar()];
+    #[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+    {
+        if is_x86_feature_detected!("sse2") {
+            v.push(Scanner {
+                name: "sse2",
+                classify: x86::classify_sse2,
+            });
+        }
+        if is_x86_feature_detected!("avx2") {
+            v.push(Scanner {
+                name: "avx2",
+                classify: x86::classify_avx2,
+            });
+        }
+    }
+    #[cfg(all(feature = "simd", target_arch = "aarch64"))]
+    {
+        if let Some(s) = detect() {
+            v.push(s);
+        }
+    }
+    v
+}
+
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+mod x86 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    use super::Masks;
+
+    // Only handed out by `detect`/`available` after checking for the feature.
+    pub fn classify_sse2(block: &[u8; 64]) -> Masks {
+        unsafe { sse2(block) }
+    }
+
+    pub fn classify_avx2(block: &[u8; 64]) -> Masks {
+        unsafe { avx2(block) }
+    }
+
+    // Compares every byte of `$v` against each of the given characters and
+    // ORs the results together.
+    macro_rules! any_eq {
+        ($cmpeq:ident, $set1:ident, $or:ident, $v:expr, $c:expr) => {
+            $cmpeq($v, $set1($c as i8))
+        };
+        ($cmpeq:ident, $set1:ident, $or:ident, $v:expr, $c:expr, $($rest:expr),+) => {
+            $or($cmpeq($v, $set1($c as i8)), any_eq!($cmpeq, $set1, $or, $v, $($rest),+))
+        };
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn sse2(block: &[u8; 64]) -> Masks {
+        macro_rules! bits {
+            ($v:expr, $($c:expr),+) => {
+                _mm_movemask_epi8(any_eq!(_mm_cmpeq_epi8, _mm_set1_epi8, _mm_or_si128,
+                                          $v, $($c),+)) as u16 as u64
+            };
+        }
+        let mut m = Masks::default();
+        for i in 0..4 {
+            let v = _mm_loadu_si128(block.as_ptr().add(i * 16) as *const __m128i);
+            let shift = i * 16;
+            m.quote |= bits!(v, b'"') << shift;
+            m.backslash |= bits!(v, b'\\') << shift;
+            m.whitespace |= bits!(v, b' ', b'\t', b'\r', b'\n') << shift;
+        }
+        m
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn avx2(block: &[u8; 64]) -> Masks {
+        macro_rules! bits {
+            ($v:expr, $($c:expr),+) => {
+                _mm256_movemask_epi8(any_eq!(_mm256_cmpeq_epi8, _mm256_set1_epi8,
+                                             _mm256_or_si256, $v, $($c),+)) as u32 as u64
+            };
+        }
+        let mut m = Masks::default();
+        for i in 0..2 {
+            let v = _mm256_loadu_si256(block.as_ptr().add(i * 32) as *const __m256i);
+            let shift = i * 32;
+            m.quote |= bits!(v, b'"') << shift;
+            m.backslash |= bits!(v, b'\\') << shift;
+            m.whitespace |= bits!(v, b' ', b'\t', b'\r', b'\n') << shift;
+        }
+        m
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+mod neon {
+    use std::arch::aarch64::*;
+
+    use super::Masks;
+
+    // Only handed out by `detect`/`available` after checking for the feature.
+    pub fn classify(block: &[u8; 64]) -> Masks {
+        unsafe { classify_neon(block) }
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn movemask(v: uint8x16_t) -> u64 {
+        const WEIGHTS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+        let m = vandq_u8(v, vld1q_u8(WEIGHTS.as_ptr()));
+        vaddv_u8(vget_low_u8(m)) as u64 | (vaddv_u8(vget_high_u8(m)) as u64) << 8
+    }
+
+    // Compares every byte of `$v` against each of the given characters and
+    // ORs the results together.
+    macro_rules! any_eq {
+        ($v:expr, $c:expr) => {
+            vceqq_u8($v, vdupq_n_u8($c))
+        };
+        ($v:expr, $c:expr, $($rest:expr),+) => {
+            vorrq_u8(vceqq_u8($v, vdupq_n_u8($c)), any_eq!($v, $($rest),+))
+        };
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn classify_neon(block: &[u8; 64]) -> Masks {
+        let mut m = Masks::default();
+        for i in 0..4 {
+            let v = vld1q_u8(block.as_ptr().add(i * 16));
+            let shift = i * 16;
+            m.quote |= movemask(any_eq!(v, b'"')) << shift;
+            m.backslash |= movemask(any_eq!(v, b'\\')) << shift;
+            m.whitespace |= movemask(any_eq!(v, b' ', b'\t', b'\r', b'\n')) << shift;
+        }
+        m
+    }
+}
@@ -0,0 +1,338 @@
+//! Inferring a JSON Schema from the documents in an event stream.
+//!
+//! [`SchemaInferrer`] records, for every path, which types were seen, the
+//! range of numbers, string lengths and array lengths, and how often each
+//! key is present in its objects. It is written out as a [JSON Schema
+//! 2020-12](https://json-schema.org/draft/2020-12/schema) document:
+//!
+//! ```text
+//! {"$schema": "https://json-schema.org/draft/2020-12/schema",
+//!  "type": "object",
+//!  "properties": {"id": {"type": "integer", "minimum": 1, "maximum": 7, "x-presence": 1.0},
+//!                 "note": {"type": ["string", "null"], "minLength": 0, "maxLength": 12,
+//!                          "x-presence": 0.5}},
+//!  "required": ["id"]}
+//! ```
+//!
+//! `x-presence` is the fraction of objects at that path that have the key.
+//! Keys present in all of them are `required`.
+
+use std::cmp::Ordering;
+use std::io::{BufRead, Write};
+
+use value::compare;
+use writer::JsonWriter;
+use {Block, Event, Parser, Result, N};
+
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// What was seen at one path.
+#[derive(Debug, Clone, Default)]
+struct Node {
+    nulls: u64,
+    bools: u64,
+    integers: u64,
+    /// Numbers with a fraction.
+    floats: u64,
+    strings: u64,
+    objects: u64,
+    arrays: u64,
+    /// Number of values seen, which for an object member is the number of
+    /// objects that had its key.
+    count: u64,
+    minimum: Option<N>,
+    maximum: Option<N>,
+    /// Shortest and longest string, in characters.
+    length: Option<(u64, u64)>,
+    /// Fewest and most array elements.
+    items: Option<(u64, u64)>,
+    /// Indexes of the member nodes, in the order the keys were first seen.
+    properties: Vec<(String, usize)>,
+    /// Index of the node for array elements.
+    element: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Frame {
+    node: usize,
+    /// Elements of an array so far.
+    len: u64,
+}
+
+/// Builds a schema from events, see the [module documentation](index.html).
+#[derive(Debug, Clone)]
+pub struct SchemaInferrer {
+    /// The root is `nodes[0]`.
+    nodes: Vec<Node>,
+    stack: Vec<Frame>,
+    /// The node for the value after a key.
+    member: Option<usize>,
+    /// The node and length so far of a string read in chunks.
+    chunks: Option<(usize, u64)>,
+    documents: u64,
+}
+
+impl Default for SchemaInferrer {
+    fn default() -> SchemaInferrer {
+        SchemaInferrer::new()
+    }
+}
+
+impl SchemaInferrer {
+    pub fn new() -> SchemaInferrer {
+        SchemaInferrer {
+            nodes: vec![Node::default()],
+            stack: Vec::new(),
+            member: None,
+            chunks: None,
+            documents: 0,
+        }
+    }
+
+    /// Reads all events of `p`, which may hold several documents.
+    pub fn infer<B: BufRead>(&mut self, p: &mut Parser<B>) -> Result<()> {
+        for ev in p {
+            self.event(&ev?);
+        }
+        Ok(())
+    }
+
+    /// Number of top-level values seen.
+    pub fn documents(&self) -> u64 {
+        self.documents
+    }
+
+    pub fn event(&mut self, ev: &Event) {
+        match *ev {
+            Event::Start(block) => {
+                let node = self.value();
+                match block {
+                    Block::Object => self.nodes[node].objects += 1,
+                    Block::Array => self.nodes[node].arrays += 1,
+                }
+                self.stack.push(Frame { node, len: 0 });
+            }
+            Event::End(block) => {
+                if let Some(frame) = self.stack.pop() {
+                    if block == Block::Array {
+                        let node = &mut self.nodes[frame.node];
+                        node.items = Some(widen(node.items, frame.len));
+                    }
+                }
+            }
+            Event::Key(ref k) => self.key(k),
+            Event::InternedKey(ref k) => self.key(k),
+            Event::RawKey(ref k) => self.key(&String::from_utf8_lossy(k)),
+            Event::String(ref s) => {
+                let node = self.value();
+                self.string(node, s.chars().count() as u64);
+            }
+            Event::Bytes(ref b) => {
+                let node = self.value();
+                self.string(node, String::from_utf8_lossy(b).chars().count() as u64);
+            }
+            Event::StringChunk(ref s) => {
+                let (node, len) = match self.chunks {
+                    Some(chunks) => chunks,
+                    None => (self.value(), 0),
+                };
+                self.chunks = Some((node, len + s.chars().count() as u64));
+            }
+            Event::StringEnd => {
+                if let Some((node, len)) = self.chunks.take() {
+                    self.string(node, len);
+                }
+            }
+            Event::Number(n) => {
+                let node = self.value();
+                let node = &mut self.nodes[node];
+                match n {
+                    N::Float(f) if f.fract() != 0.0 => node.floats += 1,
+                    _ => node.integers += 1,
+                }
+                if node.minimum.is_none_or(|min| compare(n, min) == Some(Ordering::Less)) {
+                    node.minimum = Some(n);
+                }
+                if node.maximum.is_none_or(|max| compare(n, max) == Some(Ordering::Greater)) {
+                    node.maximum = Some(n);
+                }
+            }
+            Event::Bool(_) => {
+                let node = self.value();
+                self.nodes[node].bools += 1;
+            }
+            Event::Null => {
+                let node = self.value();
+                self.nodes[node].nulls += 1;
+            }
+        }
+    }
+
+    /// Writes the schema inferred so far.
+    pub fn write_schema<W: Write>(&self, w: &mut JsonWriter<W>) -> Result<()> {
+        self.write_node(w, 0, Some(DIALECT), None)
+    }
+
+    /// The schema inferred so far as compact JSON.
+    pub fn schema(&self) -> String {
+        let mut w = JsonWriter::new(Vec::new());
+        self.write_schema(&mut w).expect("writing to a Vec");
+        String::from_utf8(w.into_inner()).unwrap()
+    }
+
+    fn key(&mut self, k: &str) {
+        let object = match self.stack.last() {
+            Some(frame) => frame.node,
+            None => return,
+        };
+        let found = self.nodes[object].properties.iter().find(|p| p.0 == k).map(|p| p.1);
+        let member = match found {
+            Some(member) => member,
+            None => {
+                self.nodes.push(Node::default());
+                let member = self.nodes.len() - 1;
+                self.nodes[object].properties.push((k.to_string(), member));
+                member
+            }
+        };
+        self.member = Some(member);
+    }
+
+    /// Counts a new value and returns its node.
+    fn value(&mut self) -> usize {
+        let node = match self.stack.last_mut() {
+            None => {
+                self.documents += 1;
+                0
+            }
+            Some(frame) => {
+                match self.member.take() {
+                    Some(member) => member,
+                    None => {
+                        frame.len += 1;
+                        match self.nodes[frame.node].element {
+                            Some(element) => element,
+                            None => {
+                                self.nodes.push(Node::default());
+                                let element = self.nodes.len() - 1;
+                                self.nodes[frame.node].element = Some(element);
+                                element
+                            }
+                        }
+                    }
+                }
+            }
+        };
+        self.nodes[node].count += 1;
+        node
+    }
+
+    fn string(&mut self, node: usize, len: u64) {
+        let node = &mut self.nodes[node];
+        node.strings += 1;
+        node.length = Some(widen(node.length, len));
+    }
+
+    fn write_node<W: Write>(&self,
+                            w: &mut JsonWriter<W>,
+                            index: usize,
+                            dialect: Option<&str>,
+                            presence: Option<f64>)
+                            -> Result<()> {
+        let node = &self.nodes[index];
+        w.write_event(&Event::Start(Block::Object))?;
+        if let Some(dialect) = dialect {
+            write_member(w, "$schema", Event::String(dialect.into()))?;
+        }
+        let mut types = Vec::new();
+        if node.objects > 0 {
+            types.push("object");
+        }
+        if node.arrays > 0 {
+            types.push("array");
+        }
+        if node.strings > 0 {
+            types.push("string");
+        }
+        if node.floats > 0 {
+            types.push("number");
+        } else if node.integers > 0 {
+            types.push("integer");
+        }
+        if node.bools > 0 {
+            types.push("boolean");
+        }
+        if node.nulls > 0 {
+            types.push("null");
+        }
+        match types.len() {
+            0 => (),
+            1 => write_member(w, "type", Event::String(types[0].into()))?,
+            _ => {
+                w.write_event(&Event::Key("type".into()))?;
+                w.write_event(&Event::Start(Block::Array))?;
+                for t in types {
+                    w.write_event(&Event::String(t.into()))?;
+                }
+                w.write_event(&Event::End(Block::Array))?;
+            }
+        }
+        if let Some(min) = node.minimum {
+            write_member(w, "minimum", Event::Number(min))?;
+        }
+        if let Some(max) = node.maximum {
+            write_member(w, "maximum", Event::Number(max))?;
+        }
+        if let Some((min, max)) = node.length {
+            write_member(w, "minLength", Event::Number(N::Uint(min)))?;
+            write_member(w, "maxLength", Event::Number(N::Uint(max)))?;
+        }
+        if let Some((min, max)) = node.items {
+            write_member(w, "minItems", Event::Number(N::Uint(min)))?;
+            write_member(w, "maxItems", Event::Number(N::Uint(max)))?;
+        }
+        if let Some(element) = node.element {
+            w.write_event(&Event::Key("items".into()))?;
+            self.write_node(w, element, None, None)?;
+        }
+        if !node.properties.is_empty() {
+            w.write_event(&Event::Key("properties".into()))?;
+            w.write_event(&Event::Start(Block::Object))?;
+            for &(ref key, member) in &node.properties {
+                let presence = self.nodes[member].count as f64 / node.objects as f64;
+                w.write_event(&Event::Key(key.clone()))?;
+                self.write_node(w, member, None, Some(presence))?;
+            }
+            w.write_event(&Event::End(Block::Object))?;
+            let required = node.properties
+                .iter()
+                .filter(|&&(_, member)| self.nodes[member].count >= node.objects);
+            let mut required = required.peekable();
+            if required.peek().is_some() {
+                w.write_event(&Event::Key("required".into()))?;
+                w.write_event(&Event::Start(Block::Array))?;
+                for (key, _) in required {
+                    w.write_event(&Event::String(key.clone()))?;
+                }
+                w.write_event(&Event::End(Block::Array))?;
+            }
+        }
+        if let Some(presence) = presence {
+            write_member(w, "x-presence", Event::Number(N::Float(presence)))?;
+        }
+        w.write_event(&Event::End(Block::Object))
+    }
+}
+
+fn write_member<W: Write>(w: &mut JsonWriter<W>, key: &str, value: Event) -> Result<()> {
+    w.write_event(&Event::Key(key.into()))?;
+    w.write_event(&value)
+}
+
+/// Extends the range `(min, max)` to include `n`.
+fn widen(range: Option<(u64, u64)>, n: u64) -> (u64, u64) {
+    match range {
+        Some((min, max)) => (min.min(n), max.max(n)),
+        None => (n, n),
+    }
+}
@@ -0,0 +1,472 @@
+//! Rewriting event streams on the fly.
+//!
+//! [`Transform`] adds adapters to any iterator of events, such as a
+//! [`Parser`](::Parser), that drop, rename or reshape parts of the document
+//! as it streams past. Given a well-formed stream, each of them yields a
+//! well-formed stream, so they can be chained and fed to a
+//! [`JsonWriter`](::writer::JsonWriter):
+//!
+//! ```
+//! # use jsonpull::*;
+//! # use jsonpull::transform::Transform;
+//! # use jsonpull::writer::JsonWriter;
+//! let p = Parser::from_reader(r#"{"a": {"b": 1, "_c": 2}, "d": [3]}"#.as_bytes());
+//! let mut w = JsonWriter::new(Vec::new());
+//! w.write_events(p.drop_keys(|k| k.starts_with('_')).flatten_keys(".")).unwrap();
+//! assert_eq!(w.into_inner(), br#"{"a.b":1,"d.0":3}"#);
+//! ```
+//!
+//! Errors from the underlying stream are passed through. After an error the
+//! output is only as well-formed as the input.
+
+use std::collections::VecDeque;
+
+use path::{self, PathTracker, Segment, Shape, Step};
+use {Block, Event, Result};
+
+pub trait Transform: Iterator<Item = Result<Event>> + Sized {
+    /// Keeps only the values whose JSON Pointer matches one of `pointers`,
+    /// along with the objects and arrays leading to them. A `*` token
+    /// matches any key or index, so `/items/*/id` keeps the `id` of every
+    /// item. Array elements are renumbered by dropping the others.
+    fn filter_paths<S: AsRef<str>>(self, pointers: &[S]) -> Result<FilterPaths<Self>> {
+        let patterns = pointers.iter()
+            .map(|p| path::parse_pointer(p.as_ref()))
+            .collect::<Result<_>>()?;
+        Ok(FilterPaths {
+            inner: self,
+            patterns,
+            tracker: PathTracker::new(),
+            frames: Vec::new(),
+            key: None,
+            matched: 0,
+            skipped: 0,
+            queue: VecDeque::new(),
+        })
+    }
+
+    /// Renames every key for which `f` returns a new name.
+    fn rename_keys<F>(self, f: F) -> RenameKeys<Self, F>
+        where F: FnMut(&str) -> Option<String>
+    {
+        RenameKeys { inner: self, f }
+    }
+
+    /// Drops every member, at any depth, whose key `f` returns true for.
+    fn drop_keys<F>(self, f: F) -> DropKeys<Self, F>
+        where F: FnMut(&str) -> bool
+    {
+        DropKeys {
+            inner: self,
+            f,
+            shape: Shape::new(),
+            skipped: 0,
+            dropping: false,
+        }
+    }
+
+    /// Replaces every string, number, boolean and `null` with what `f`
+    /// returns for it, which must be one of those too. Strings read in
+    /// chunks are passed through unchanged.
+    fn map_values<F>(self, f: F) -> MapValues<Self, F>
+        where F: FnMut(Event) -> Event
+    {
+        MapValues { inner: self, f }
+    }
+
+    /// Turns every top-level object or array into a single object, with the
+    /// keys and indices on the path to each value joined by `separator` as
+    /// its key. Empty objects and arrays are kept as values.
+    ///
+    /// Named so as not to clash with `Iterator::flatten`.
+    fn flatten_keys(self, separator: &str) -> Flatten<Self> {
+        Flatten {
+            inner: self,
+            separator: separator.to_string(),
+            tracker: PathTracker::new(),
+            depth: 0,
+            pending: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Empties every object and array nested deeper than `depth`, where the
+    /// top-level value is at depth 1.
+    fn take_depth(self, depth: usize) -> TakeDepth<Self> {
+        TakeDepth {
+            inner: self,
+            max: depth,
+            depth: 0,
+            skipped: 0,
+            end: None,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<Event>>> Transform for I {}
+
+/// Counts the nesting of a value that is being dropped or passed through
+/// whole, which has ended when the count is back at zero.
+fn nest(count: &mut usize, step: Step) {
+    match step {
+        Step::Open => *count += 1,
+        Step::Close => *count -= 1,
+        _ => (),
+    }
+}
+
+#[derive(Debug)]
+struct FilterFrame {
+    /// The key of the container in its parent, and its start.
+    start: Vec<Event>,
+    emitted: bool,
+}
+
+/// See [`Transform::filter_paths`].
+#[derive(Debug)]
+pub struct FilterPaths<I> {
+    inner: I,
+    patterns: Vec<Vec<String>>,
+    tracker: PathTracker,
+    /// Containers that may hold matching values.
+    frames: Vec<FilterFrame>,
+    key: Option<Event>,
+    /// Nesting inside a matching value.
+    matched: usize,
+    /// Nesting inside a value that is dropped.
+    skipped: usize,
+    queue: VecDeque<Event>,
+}
+
+fn segment_matches(pattern: &str, segment: &Segment) -> bool {
+    match *segment {
+        _ if pattern == "*" => true,
+        Segment::Key(ref k) => k == pattern,
+        Segment::Index(i) => pattern.parse() == Ok(i),
+    }
+}
+
+impl<I: Iterator<Item = Result<Event>>> FilterPaths<I> {
+    /// Whether the current path is matched by a pattern, or leads to a
+    /// value that could be.
+    fn matches(&self) -> (bool, bool) {
+        let path: Vec<&Segment> = self.tracker.segments().collect();
+        let mut full = false;
+        let mut prefix = false;
+        for pattern in &self.patterns {
+            if pattern.len() < path.len() {
+                continue;
+            }
+            if pattern.iter().zip(&path).all(|(p, s)| segment_matches(p, s)) {
+                if pattern.len() == path.len() {
+                    full = true;
+                } else {
+                    prefix = true;
+                }
+            }
+        }
+        (full, prefix)
+    }
+
+    fn process(&mut self, ev: Event) {
+        let step = self.tracker.event(&ev);
+        if self.skipped > 0 {
+            nest(&mut self.skipped, step);
+            return;
+        }
+        if self.matched > 0 {
+            nest(&mut self.matched, step);
+            self.queue.push_back(ev);
+            return;
+        }
+        match step {
+            Step::Key => self.key = Some(ev),
+            Step::Close => {
+                if let Some(frame) = self.frames.pop() {
+                    if frame.emitted {
+                        self.queue.push_back(ev);
+                    }
+                }
+            }
+            Step::Inner => (),
+            Step::Open | Step::Scalar => {
+                let (full, prefix) = self.matches();
+                if full {
+                    for frame in &mut self.frames {
+                        if !frame.emitted {
+                            frame.emitted = true;
+                            self.queue.extend(frame.start.drain(..));
+                        }
+                    }
+                    self.queue.extend(self.key.take());
+                    self.queue.push_back(ev);
+                    if step == Step::Open {
+                        self.matched = 1;
+                    }
+                } else if prefix && matches!(ev, Event::Start(_)) {
+                    let mut start: Vec<Event> = self.key.take().into_iter().collect();
+                    start.push(ev);
+                    self.frames.push(FilterFrame {
+                        start,
+                        emitted: false,
+                    });
+                } else {
+                    self.key = None;
+                    if step == Step::Open {
+                        self.skipped = 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<Event>>> Iterator for FilterPaths<I> {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Result<Event>> {
+        loop {
+            if let Some(ev) = self.queue.pop_front() {
+                return Some(Ok(ev));
+            }
+            match self.inner.next()? {
+                Ok(ev) => self.process(ev),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// See [`Transform::rename_keys`].
+#[derive(Debug)]
+pub struct RenameKeys<I, F> {
+    inner: I,
+    f: F,
+}
+
+impl<I, F> Iterator for RenameKeys<I, F>
+    where I: Iterator<Item = Result<Event>>,
+          F: FnMut(&str) -> Option<String>
+{
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Result<Event>> {
+        let ev = match self.inner.next()? {
+            Ok(ev) => ev,
+            Err(e) => return Some(Err(e)),
+        };
+        let renamed = ev.key().and_then(|k| (self.f)(k));
+        Some(Ok(renamed.map_or(ev, Event::Key)))
+    }
+}
+
+/// See [`Transform::drop_keys`].
+#[derive(Debug)]
+pub struct DropKeys<I, F> {
+    inner: I,
+    f: F,
+    shape: Shape,
+    skipped: usize,
+    /// The value after the last key is to be dropped.
+    dropping: bool,
+}
+
+impl<I, F> Iterator for DropKeys<I, F>
+    where I: Iterator<Item = Result<Event>>,
+          F: FnMut(&str) -> bool
+{
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Result<Event>> {
+        loop {
+            let ev = match self.inner.next()? {
+                Ok(ev) => ev,
+                Err(e) => return Some(Err(e)),
+            };
+            let step = self.shape.step(&ev);
+            if self.skipped > 0 {
+                nest(&mut self.skipped, step);
+                continue;
+            }
+            if self.dropping {
+                self.dropping = false;
+                if step == Step::Open {
+                    self.skipped = 1;
+                }
+                continue;
+            }
+            if let Some(k) = ev.key() {
+                if (self.f)(k) {
+                    self.dropping = true;
+                    continue;
+                }
+            }
+            return Some(Ok(ev));
+        }
+    }
+}
+
+/// See [`Transform::map_values`].
+#[derive(Debug)]
+pub struct MapValues<I, F> {
+    inner: I,
+    f: F,
+}
+
+impl<I, F> Iterator for MapValues<I, F>
+    where I: Iterator<Item = Result<Event>>,
+          F: FnMut(Event) -> Event
+{
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Result<Event>> {
+        let ev = match self.inner.next()? {
+            Ok(ev) => ev,
+            Err(e) => return Some(Err(e)),
+        };
+        if !is_scalar(&ev) {
+            return Some(Ok(ev));
+        }
+        let ev = (self.f)(ev);
+        if is_scalar(&ev) {
+            Some(Ok(ev))
+        } else {
+            Some(Err(format!("map_values must return a scalar, not {:?}", ev).into()))
+        }
+    }
+}
+
+fn is_scalar(ev: &Event) -> bool {
+    matches!(*ev,
+             Event::String(_) | Event::Bytes(_) | Event::Number(_) | Event::Bool(_) | Event::Null)
+}
+
+/// See [`Transform::flatten_keys`].
+#[derive(Debug)]
+pub struct Flatten<I> {
+    inner: I,
+    separator: String,
+    tracker: PathTracker,
+    depth: usize,
+    /// A nested container that has started, and its key, until it is known
+    /// whether it is empty.
+    pending: Option<(String, Event)>,
+    queue: VecDeque<Event>,
+}
+
+impl<I: Iterator<Item = Result<Event>>> Flatten<I> {
+    fn key(&self) -> String {
+        let mut key = String::new();
+        for (i, segment) in self.tracker.segments().enumerate() {
+            if i > 0 {
+                key.push_str(&self.separator);
+            }
+            match *segment {
+                Segment::Key(ref k) => key.push_str(k),
+                Segment::Index(n) => key.push_str(&n.to_string()),
+            }
+        }
+        key
+    }
+
+    fn process(&mut self, ev: Event) {
+        let step = self.tracker.event(&ev);
+        if let Some((key, start)) = self.pending.take() {
+            if let Event::End(_) = ev {
+                self.queue.extend(vec![Event::Key(key), start, ev]);
+                self.depth -= 1;
+                return;
+            }
+        }
+        match ev {
+            Event::Start(_) if self.depth == 0 => {
+                self.depth = 1;
+                self.queue.push_back(Event::Start(Block::Object));
+            }
+            Event::Start(_) => {
+                self.depth += 1;
+                self.pending = Some((self.key(), ev));
+            }
+            Event::End(_) => {
+                self.depth -= 1;
+                if self.depth == 0 {
+                    self.queue.push_back(Event::End(Block::Object));
+                }
+            }
+            _ if self.depth == 0 => self.queue.push_back(ev),
+            _ => {
+                match step {
+                    Step::Key => (),
+                    Step::Scalar | Step::Open => {
+                        let key = self.key();
+                        self.queue.push_back(Event::Key(key));
+                        self.queue.push_back(ev);
+                    }
+                    Step::Inner | Step::Close => self.queue.push_back(ev),
+                }
+            }
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<Event>>> Iterator for Flatten<I> {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Result<Event>> {
+        loop {
+            if let Some(ev) = self.queue.pop_front() {
+                return Some(Ok(ev));
+            }
+            match self.inner.next()? {
+                Ok(ev) => self.process(ev),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// See [`Transform::take_depth`].
+#[derive(Debug)]
+pub struct TakeDepth<I> {
+    inner: I,
+    max: usize,
+    depth: usize,
+    /// Nesting inside a container that is emptied.
+    skipped: usize,
+    end: Option<Event>,
+}
+
+impl<I: Iterator<Item = Result<Event>>> Iterator for TakeDepth<I> {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Result<Event>> {
+        if let Some(end) = self.end.take() {
+            return Some(Ok(end));
+        }
+        loop {
+            let ev = match self.inner.next()? {
+                Ok(ev) => ev,
+                Err(e) => return Some(Err(e)),
+            };
+            match ev {
+                Event::Start(block) if self.skipped == 0 && self.depth == self.max => {
+                    self.skipped = 1;
+                    self.end = Some(Event::End(block));
+                    return Some(Ok(ev));
+                }
+                Event::Start(_) if self.skipped > 0 => self.skipped += 1,
+                Event::End(_) if self.skipped > 0 => self.skipped -= 1,
+                _ if self.skipped > 0 => (),
+                Event::Start(_) => {
+                    self.depth += 1;
+                    return Some(Ok(ev));
+                }
+                Event::End(_) => {
+                    self.depth -= 1;
+                    return Some(Ok(ev));
+                }
+                _ => return Some(Ok(ev)),
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Typed accessors for writing streaming decoders by hand.
+//!
+//! Each method reads the events of one value and fails with
+//! `ErrorKind::Unexpected`, naming what was found and where, if they are not
+//! of the expected type.
+
+use std::io::BufRead;
+
+use {Block, ChunkState, Error, ErrorKind, Event, N, Parser, Position, Raw, Result, StrKind};
+
+impl<B: BufRead> Parser<B> {
+    pub fn expect_object_start(&mut self) -> Result<()> {
+        self.expect_event(Event::Start(Block::Object), "start of object")
+    }
+
+    pub fn expect_object_end(&mut self) -> Result<()> {
+        self.expect_event(Event::End(Block::Object), "end of object")
+    }
+
+    pub fn expect_array_start(&mut self) -> Result<()> {
+        self.expect_event(Event::Start(Block::Array), "start of array")
+    }
+
+    pub fn expect_array_end(&mut self) -> Result<()> {
+        self.expect_event(Event::End(Block::Array), "end of array")
+    }
+
+    /// Reads the next key of the current object, or its end, in which case
+    /// `None` is returned.
+    pub fn next_key(&mut self) -> Result<Option<String>> {
+        const EXPECTED: &str = "key or end of object";
+        match self.next_event(EXPECTED)? {
+            Event::Key(k) => Ok(Some(k)),
+            Event::InternedKey(k) => Ok(Some(k.to_string())),
+            Event::End(Block::Object) => Ok(None),
+            ev => Err(self.unexpected(EXPECTED, Some(&ev))),
+        }
+    }
+
+    /// Reads a string, joining its chunks if it was split up.
+    pub fn read_string(&mut self) -> Result<String> {
+        match self.next_event("string")? {
+            Event::String(s) => Ok(s),
+            Event::StringChunk(s) => self.read_chunks(s),
+            ev => Err(self.unexpected("string", Some(&ev))),
+        }
+    }
+
+    /// Reads a string or `null`.
+    pub fn read_opt_string(&mut self) -> Result<Option<String>> {
+        const EXPECTED: &str = "string or null";
+        match self.next_event(EXPECTED)? {
+            Event::String(s) => Ok(Some(s)),
+            Event::StringChunk(s) => self.read_chunks(s).map(Some),
+            Event::Null => Ok(None),
+            ev => Err(self.unexpected(EXPECTED, Some(&ev))),
+        }
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool> {
+        match self.next_event("boolean")? {
+            Event::Bool(b) => Ok(b),
+            ev => Err(self.unexpected("boolean", Some(&ev))),
+        }
+    }
+
+    pub fn read_null(&mut self) -> Result<()> {
+        self.expect_event(Event::Null, "null")
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64> {
+        const EXPECTED: &str = "unsigned integer";
+        match self.next_event(EXPECTED)? {
+            Event::Number(N::Uint(n)) => Ok(n),
+            ev => Err(self.unexpected(EXPECTED, Some(&ev))),
+        }
+    }
+
+    pub fn read_i64(&mut self) -> Result<i64> {
+        const EXPECTED: &str = "integer";
+        match self.next_event(EXPECTED)? {
+            Event::Number(N::Int(n)) => Ok(n),
+            Event::Number(N::Uint(n)) if n <= i64::MAX as u64 => Ok(n as i64),
+            ev => Err(self.unexpected(EXPECTED, Some(&ev))),
+        }
+    }
+
+    /// Reads any number, converting integers to the nearest `f64`.
+    pub fn read_f64(&mut self) -> Result<f64> {
+        match self.next_event("number")? {
+            Event::Number(N::Float(n)) => Ok(n),
+            Event::Number(N::Int(n)) => Ok(n as f64),
+            Event::Number(N::Uint(n)) => Ok(n as f64),
+            ev => Err(self.unexpected("number", Some(&ev))),
+        }
+    }
+
+    /// Skips the next value, including everything inside it if it is an
+    /// object or array. Strings are not checked for valid UTF-8.
+    pub fn skip_value(&mut self) -> Result<()> {
+        let mut depth = 0;
+        loop {
+            match self.next_raw() {
+                None => return Err(self.unexpected("value", None)),
+                Some(Err(e)) => return Err(e),
+                Some(Ok(Raw::Event(Event::Start(_)))) => depth += 1,
+                Some(Ok(Raw::Event(Event::End(b)))) => {
+                    if depth == 0 {
+                        return Err(self.unexpected("value", Some(&Event::End(b))));
+                    }
+                    depth -= 1;
+                }
+                Some(Ok(Raw::Str(StrKind::Key))) if depth == 0 => {
+                    let ev = self.owned_event(Raw::Str(StrKind::Key))?;
+                    return Err(self.unexpected("value", Some(&ev)));
+                }
+                Some(Ok(_)) => (),
+            }
+            if depth == 0 && self.chunk_state == ChunkState::None {
+                return Ok(());
+            }
+        }
+    }
+
+    fn read_chunks(&mut self, mut s: String) -> Result<String> {
+        loop {
+            match self.next_event("end of string")? {
+                Event::StringChunk(chunk) => s.push_str(&chunk),
+                Event::StringEnd => return Ok(s),
+                ev => return Err(self.unexpected("end of string", Some(&ev))),
+            }
+        }
+    }
+
+    fn expect_event(&mut self, expected_event: Event, expected: &'static str) -> Result<()> {
+        let ev = self.next_event(expected)?;
+        if ev == expected_event {
+            Ok(())
+        } else {
+            Err(self.unexpected(expected, Some(&ev)))
+        }
+    }
+
+    /// Reads the next event, failing at the end of the input.
+    fn next_event(&mut self, expected: &'static str) -> Result<Event> {
+        match self.next() {
+            Some(r) => r,
+            None => Err(self.unexpected(expected, None)),
+        }
+    }
+
+    pub(crate) fn unexpected(&self, expected: &'static str, found: Option<&Event>) -> Error {
+        let at = match found {
+            Some(_) => {
+                Position {
+                    offset: self.event_offset,
+                    line: self.position().line,
+                }
+            }
+            None => self.position(),
+        };
+        ErrorKind::Unexpected(expected, describe(found), at).into()
+    }
+}
+
+fn describe(ev: Option<&Event>) -> String {
+    let ev = match ev {
+        Some(ev) => ev,
+        None => return "end of input".into(),
+    };
+    match *ev {
+        Event::Start(Block::Object) => "start of object".into(),
+        Event::Start(Block::Array) => "start of array".into(),
+        Event::End(Block::Object) => "end of object".into(),
+        Event::End(Block::Array) => "end of array".into(),
+        Event::Key(ref k) => format!("key {:?}", k),
+        Event::InternedKey(ref k) => format!("key {:?}", k),
+        Event::RawKey(_) => "key that is not valid UTF-8".into(),
+        Event::String(_) | Event::StringChunk(_) => "string".into(),
+        Event::StringEnd => "end of string".into(),
+        Event::Bytes(_) => "string that is not valid UTF-8".into(),
+        Event::Number(N::Float(n)) => format!("number {}", n),
+        Event::Number(N::Int(n)) => format!("number {}", n),
+        Event::Number(N::Uint(n)) => format!("number {}", n),
+        Event::Bool(b) => b.to_string(),
+        Event::Null => "null".into(),
+    }
+}
@@ -0,0 +1,669 @@
+//! Validating documents against a JSON Schema while they are read.
+//!
+//! The supported keywords are `type`, `properties`, `required`,
+//! `additionalProperties`, `items`, `enum`, `const`, `minimum`, `maximum`,
+//! `exclusiveMinimum`, `exclusiveMaximum`, `minLength`, `maxLength`,
+//! `minItems`, `maxItems`, `pattern` (with the `regex` feature) and `$ref`
+//! to a JSON Pointer within the schema, like `#/$defs/item`. Other keywords
+//! are ignored.
+//!
+//! Only the values checked by `enum` or `const` are kept in memory, and
+//! strings checked by `pattern`; everything else is checked event by event,
+//! so a violation is found as soon as the offending value has been read:
+//!
+//! ```
+//! # use jsonpull::Parser;
+//! # use jsonpull::validate::Schema;
+//! let schema = Schema::parse(r#"{"type": "array", "items": {"type": "integer"}}"#).unwrap();
+//! let mut p = Parser::from_reader(&b"[1, 2, \"3\", 4]"[..]);
+//! let violations = schema.validate(&mut p).unwrap();
+//! assert_eq!(violations[0].pointer, "/2");
+//! assert_eq!(violations[0].keyword, "type");
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::BufRead;
+use std::mem;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+use path::{self, PathTracker, Step};
+use value::{self, Value, ValueBuilder};
+use {Block, Event, Parser, Position, Result, N};
+
+const NULL: u8 = 1;
+const BOOLEAN: u8 = 1 << 1;
+const OBJECT: u8 = 1 << 2;
+const ARRAY: u8 = 1 << 3;
+const NUMBER: u8 = 1 << 4;
+const INTEGER: u8 = 1 << 5;
+const STRING: u8 = 1 << 6;
+
+const TYPES: [(&str, u8); 7] = [("null", NULL),
+                                ("boolean", BOOLEAN),
+                                ("object", OBJECT),
+                                ("array", ARRAY),
+                                ("number", NUMBER),
+                                ("integer", INTEGER),
+                                ("string", STRING)];
+
+/// A compiled subschema.
+#[derive(Debug, Default)]
+struct Node {
+    /// The `false` schema.
+    reject: bool,
+    /// Allowed types, any when 0.
+    types: u8,
+    properties: Vec<(String, usize)>,
+    required: Vec<String>,
+    additional: Option<usize>,
+    items: Option<usize>,
+    one_of: Option<Vec<Value>>,
+    constant: Option<Value>,
+    minimum: Option<N>,
+    maximum: Option<N>,
+    exclusive_minimum: Option<N>,
+    exclusive_maximum: Option<N>,
+    min_length: Option<u64>,
+    max_length: Option<u64>,
+    min_items: Option<u64>,
+    max_items: Option<u64>,
+    #[cfg(feature = "regex")]
+    pattern: Option<Regex>,
+    reference: Option<usize>,
+}
+
+impl Node {
+    /// Whether values must be kept whole to check them.
+    fn captures(&self) -> bool {
+        self.one_of.is_some() || self.constant.is_some()
+    }
+
+    #[cfg(feature = "regex")]
+    fn needs_text(&self) -> bool {
+        self.captures() || self.pattern.is_some()
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn needs_text(&self) -> bool {
+        self.captures()
+    }
+}
+
+/// A compiled JSON Schema.
+#[derive(Debug)]
+pub struct Schema {
+    /// The root is `nodes[0]`.
+    nodes: Vec<Node>,
+}
+
+/// A value that does not match the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// JSON Pointer to the value.
+    pub pointer: String,
+    /// Where the value starts in the input.
+    pub position: Position,
+    /// The schema keyword that failed.
+    pub keyword: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{} at {} (line {}, offset {})",
+               self.message,
+               if self.pointer.is_empty() { "the root" } else { &self.pointer },
+               self.position.line,
+               self.position.offset)
+    }
+}
+
+/// Compiles schemas, following `$ref`s once each.
+struct Compiler<'a> {
+    root: &'a Value,
+    nodes: Vec<Node>,
+    /// Node of each subschema compiled so far, by JSON Pointer.
+    compiled: HashMap<String, usize>,
+    /// Nodes with a `$ref` still to resolve.
+    refs: Vec<(usize, String)>,
+}
+
+impl<'a> Compiler<'a> {
+    fn compile(&mut self, schema: &Value, pointer: String) -> Result<usize> {
+        if let Some(&node) = self.compiled.get(&pointer) {
+            return Ok(node);
+        }
+        let index = self.nodes.len();
+        self.nodes.push(Node::default());
+        self.compiled.insert(pointer.clone(), index);
+        let members = match *schema {
+            Value::Bool(true) => return Ok(index),
+            Value::Bool(false) => {
+                self.nodes[index].reject = true;
+                return Ok(index);
+            }
+            Value::Object(ref members) => members,
+            _ => return Err(format!("schema at #{} is not an object or boolean", pointer).into()),
+        };
+        let mut node = Node::default();
+        for (keyword, value) in members {
+            let at = || format!("{}/{}", pointer, path::escape_token(keyword));
+            let invalid = || -> ::Error { format!("invalid `{}` at #{}", keyword, pointer).into() };
+            match keyword.as_str() {
+                "type" => {
+                    let names = match *value {
+                        Value::String(_) => vec![value],
+                        Value::Array(ref names) => names.iter().collect(),
+                        _ => return Err(invalid()),
+                    };
+                    for name in names {
+                        node.types |= TYPES.iter()
+                            .find(|t| Some(t.0) == name.as_str())
+                            .ok_or_else(invalid)?
+                            .1;
+                    }
+                }
+                "properties" => {
+                    for (key, schema) in value.as_object().ok_or_else(invalid)? {
+                        let at = format!("{}/{}", at(), path::escape_token(key));
+                        node.properties.push((key.clone(), self.compile(schema, at)?));
+                    }
+                }
+                "required" => {
+                    for key in value.as_array().ok_or_else(invalid)? {
+                        node.required.push(key.as_str().ok_or_else(invalid)?.to_string());
+                    }
+                }
+                "additionalProperties" => node.additional = Some(self.compile(value, at())?),
+                "items" => node.items = Some(self.compile(value, at())?),
+                "enum" => node.one_of = Some(value.as_array().ok_or_else(invalid)?.to_vec()),
+                "const" => node.constant = Some(value.clone()),
+                "minimum" => node.minimum = Some(number(value).ok_or_else(invalid)?),
+                "maximum" => node.maximum = Some(number(value).ok_or_else(invalid)?),
+                "exclusiveMinimum" => {
+                    node.exclusive_minimum = Some(number(value).ok_or_else(invalid)?)
+                }
+                "exclusiveMaximum" => {
+                    node.exclusive_maximum = Some(number(value).ok_or_else(invalid)?)
+                }
+                "minLength" => node.min_length = Some(value.as_u64().ok_or_else(invalid)?),
+                "maxLength" => node.max_length = Some(value.as_u64().ok_or_else(invalid)?),
+                "minItems" => node.min_items = Some(value.as_u64().ok_or_else(invalid)?),
+                "maxItems" => node.max_items = Some(value.as_u64().ok_or_else(invalid)?),
+                "pattern" => pattern(&mut node, value.as_str().ok_or_else(invalid)?)?,
+                "$ref" => {
+                    let reference = value.as_str().ok_or_else(invalid)?;
+                    self.refs.push((index, reference.to_string()));
+                }
+                _ => (),
+            }
+        }
+        self.nodes[index] = node;
+        Ok(index)
+    }
+
+    fn resolve_refs(&mut self) -> Result<()> {
+        while let Some((node, reference)) = self.refs.pop() {
+            let pointer = match reference.strip_prefix('#') {
+                Some(pointer) => pointer,
+                None => return Err(format!("unsupported $ref `{}`", reference).into()),
+            };
+            let root = self.root;
+            let schema = root.pointer(pointer)
+                .ok_or_else(|| format!("unresolved $ref `{}`", reference))?;
+            let target = self.compile(schema, pointer.to_string())?;
+            self.nodes[node].reference = Some(target);
+        }
+        Ok(())
+    }
+}
+
+fn number(value: &Value) -> Option<N> {
+    match *value {
+        Value::Number(n) => Some(n),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "regex")]
+fn pattern(node: &mut Node, pattern: &str) -> Result<()> {
+    let regex = Regex::new(pattern).map_err(|e| format!("invalid pattern: {}", e))?;
+    node.pattern = Some(regex);
+    Ok(())
+}
+
+#[cfg(not(feature = "regex"))]
+fn pattern(_: &mut Node, _: &str) -> Result<()> {
+    Err("`pattern` needs the `regex` feature".into())
+}
+
+impl Schema {
+    pub fn new(schema: &Value) -> Result<Schema> {
+        let mut compiler = Compiler {
+            root: schema,
+            nodes: Vec::new(),
+            compiled: HashMap::new(),
+            refs: Vec::new(),
+        };
+        compiler.compile(schema, String::new())?;
+        compiler.resolve_refs()?;
+        Ok(Schema { nodes: compiler.nodes })
+    }
+
+    pub fn parse(json: &str) -> Result<Schema> {
+        Schema::new(&Value::parse(json)?)
+    }
+
+    /// Reads all events of `p` and returns every violation found.
+    pub fn validate<B: BufRead>(&self, p: &mut Parser<B>) -> Result<Vec<Violation>> {
+        let mut violations = Vec::new();
+        self.validate_with(p, |v| {
+            violations.push(v);
+            true
+        })?;
+        Ok(violations)
+    }
+
+    /// Reads events of `p`, passing each violation to `f` as soon as it is
+    /// found. Stops early when `f` returns `false`. Returns whether the input
+    /// was valid.
+    pub fn validate_with<B, F>(&self, p: &mut Parser<B>, mut f: F) -> Result<bool>
+        where B: BufRead,
+              F: FnMut(Violation) -> bool
+    {
+        let mut validator = Validator::new(self);
+        let mut valid = true;
+        while let Some(ev) = p.next() {
+            let ev = ev?;
+            let at = Position {
+                offset: p.event_offset(),
+                line: Parser::position(p).line,
+            };
+            validator.event(&ev, at);
+            for violation in validator.take_violations() {
+                valid = false;
+                if !f(violation) {
+                    return Ok(false);
+                }
+            }
+        }
+        // Checks like `required` only run once a value is complete.
+        if !validator.stack.is_empty() || validator.chunks.is_some() {
+            return Err("unexpected EOF".into());
+        }
+        Ok(valid)
+    }
+}
+
+#[derive(Debug)]
+struct Frame {
+    block: Block,
+    /// Schemas the container is checked against.
+    schemas: Vec<usize>,
+    start: Position,
+    /// Members or elements so far.
+    len: u64,
+    /// Keys seen, when some schema has `required`.
+    keys: Option<HashSet<String>>,
+    /// The container so far, when some schema has `enum` or `const`.
+    capture: Option<ValueBuilder>,
+}
+
+/// A string read in chunks.
+#[derive(Debug)]
+struct Chunks {
+    schemas: Vec<usize>,
+    start: Position,
+    len: u64,
+    /// The string so far, when some schema needs it whole.
+    text: Option<String>,
+}
+
+/// Checks events one at a time, for use outside of [`Schema::validate`].
+#[derive(Debug)]
+pub struct Validator<'s> {
+    schema: &'s Schema,
+    stack: Vec<Frame>,
+    tracker: PathTracker,
+    /// Schemas of the value after a key.
+    member: Vec<usize>,
+    chunks: Option<Chunks>,
+    violations: Vec<Violation>,
+}
+
+impl<'s> Validator<'s> {
+    pub fn new(schema: &'s Schema) -> Validator<'s> {
+        Validator {
+            schema,
+            stack: Vec::new(),
+            tracker: PathTracker::new(),
+            member: Vec::new(),
+            chunks: None,
+            violations: Vec::new(),
+        }
+    }
+
+    /// Violations found so far.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    pub fn take_violations(&mut self) -> Vec<Violation> {
+        mem::take(&mut self.violations)
+    }
+
+    /// Checks an event that started at `at`.
+    pub fn event(&mut self, ev: &Event, at: Position) {
+        let step = self.tracker.event(ev);
+        let mut closed = None;
+        for frame in &mut self.stack {
+            if let Some(ref mut capture) = frame.capture {
+                if let Some(value) = capture.event(ev.clone()) {
+                    closed = Some(value);
+                }
+            }
+        }
+        let nodes = &self.schema.nodes;
+        match *ev {
+            Event::Start(block) => {
+                let schemas = self.value_schemas();
+                self.check_type(&schemas, if block == Block::Object { OBJECT } else { ARRAY }, at);
+                let keys = if schemas.iter().any(|&s| !nodes[s].required.is_empty()) {
+                    Some(HashSet::new())
+                } else {
+                    None
+                };
+                let capture = if schemas.iter().any(|&s| nodes[s].captures()) {
+                    let mut builder = ValueBuilder::new();
+                    builder.event(ev.clone());
+                    Some(builder)
+                } else {
+                    None
+                };
+                self.stack.push(Frame {
+                    block,
+                    schemas,
+                    start: at,
+                    len: 0,
+                    keys,
+                    capture,
+                });
+            }
+            Event::End(_) => {
+                if let Some(frame) = self.stack.pop() {
+                    self.end(frame, closed);
+                }
+            }
+            Event::Key(ref k) => self.key(k, at),
+            Event::InternedKey(ref k) => self.key(k, at),
+            Event::RawKey(ref k) => self.key(&String::from_utf8_lossy(k), at),
+            Event::String(ref s) => {
+                let schemas = self.value_schemas();
+                self.check_type(&schemas, STRING, at);
+                self.check_string(&schemas, s.chars().count() as u64, Some(s), at);
+            }
+            Event::Bytes(ref b) => {
+                let schemas = self.value_schemas();
+                self.check_type(&schemas, STRING, at);
+                let s = String::from_utf8_lossy(b);
+                self.check_string(&schemas, s.chars().count() as u64, Some(&s), at);
+            }
+            Event::StringChunk(ref s) => {
+                if step == Step::Open {
+                    let schemas = self.value_schemas();
+                    self.check_type(&schemas, STRING, at);
+                    let text = if schemas.iter().any(|&s| nodes[s].needs_text()) {
+                        Some(String::new())
+                    } else {
+                        None
+                    };
+                    self.chunks = Some(Chunks {
+                        schemas,
+                        start: at,
+                        len: 0,
+                        text,
+                    });
+                }
+                let chunks = self.chunks.as_mut().unwrap();
+                chunks.len += s.chars().count() as u64;
+                if let Some(ref mut text) = chunks.text {
+                    text.push_str(s);
+                }
+            }
+            Event::StringEnd => {
+                if let Some(chunks) = self.chunks.take() {
+                    self.check_string(&chunks.schemas,
+                                      chunks.len,
+                                      chunks.text.as_deref(),
+                                      chunks.start);
+                }
+            }
+            Event::Number(n) => {
+                let schemas = self.value_schemas();
+                let integral = match n {
+                    N::Float(f) => f.fract() == 0.0,
+                    _ => true,
+                };
+                self.check_type(&schemas, if integral { INTEGER } else { NUMBER }, at);
+                self.check_number(&schemas, n, at);
+                self.check_value(&schemas, &Value::Number(n), at);
+            }
+            Event::Bool(b) => {
+                let schemas = self.value_schemas();
+                self.check_type(&schemas, BOOLEAN, at);
+                self.check_value(&schemas, &Value::Bool(b), at);
+            }
+            Event::Null => {
+                let schemas = self.value_schemas();
+                self.check_type(&schemas, NULL, at);
+                self.check_value(&schemas, &Value::Null, at);
+            }
+        }
+    }
+
+    /// The schemas of the value starting now, with `$ref`s followed.
+    fn value_schemas(&mut self) -> Vec<usize> {
+        let nodes = &self.schema.nodes;
+        let direct = match self.stack.last_mut() {
+            None => vec![0],
+            Some(frame) if frame.block == Block::Object => mem::take(&mut self.member),
+            Some(frame) => {
+                frame.len += 1;
+                frame.schemas.iter().filter_map(|&s| nodes[s].items).collect()
+            }
+        };
+        let mut schemas = Vec::new();
+        for mut s in direct {
+            while !schemas.contains(&s) {
+                schemas.push(s);
+                match nodes[s].reference {
+                    Some(target) => s = target,
+                    None => break,
+                }
+            }
+        }
+        schemas
+    }
+
+    fn key(&mut self, key: &str, at: Position) {
+        let nodes = &self.schema.nodes;
+        let frame = match self.stack.last_mut() {
+            Some(frame) => frame,
+            None => return,
+        };
+        frame.len += 1;
+        if let Some(ref mut keys) = frame.keys {
+            keys.insert(key.to_string());
+        }
+        let mut rejected = false;
+        self.member.clear();
+        for &s in &frame.schemas {
+            match nodes[s].properties.iter().find(|p| p.0 == key) {
+                Some(&(_, property)) => self.member.push(property),
+                None => {
+                    match nodes[s].additional {
+                        Some(extra) if nodes[extra].reject => rejected = true,
+                        Some(extra) => self.member.push(extra),
+                        None => (),
+                    }
+                }
+            }
+        }
+        if rejected {
+            self.report(at, "additionalProperties", format!("property `{}` is not allowed", key));
+        }
+    }
+
+    fn end(&mut self, frame: Frame, closed: Option<Value>) {
+        let nodes = &self.schema.nodes;
+        for &s in &frame.schemas {
+            let node = &nodes[s];
+            if let Some(min) = node.min_items {
+                if frame.block == Block::Array && frame.len < min {
+                    self.report(frame.start,
+                                "minItems",
+                                format!("{} items, fewer than {}", frame.len, min));
+                }
+            }
+            if let Some(max) = node.max_items {
+                if frame.block == Block::Array && frame.len > max {
+                    self.report(frame.start,
+                                "maxItems",
+                                format!("{} items, more than {}", frame.len, max));
+                }
+            }
+            if let Some(ref keys) = frame.keys {
+                for key in &node.required {
+                    if frame.block == Block::Object && !keys.contains(key) {
+                        self.report(frame.start,
+                                    "required",
+                                    format!("missing required property `{}`", key));
+                    }
+                }
+            }
+        }
+        if let Some(value) = closed {
+            self.check_value(&frame.schemas, &value, frame.start);
+        }
+    }
+
+    fn check_type(&mut self, schemas: &[usize], t: u8, at: Position) {
+        let nodes = &self.schema.nodes;
+        for &s in schemas {
+            let node = &nodes[s];
+            if node.reject {
+                self.report(at, "false", "no value is allowed here".into());
+            } else if node.types != 0 && node.types & t == 0 &&
+                      !(t == INTEGER && node.types & NUMBER != 0) {
+                let message = format!("expected {}, found {}",
+                                      type_names(node.types),
+                                      type_names(t));
+                self.report(at, "type", message);
+            }
+        }
+    }
+
+    fn check_string(&mut self, schemas: &[usize], len: u64, s: Option<&str>, at: Position) {
+        let nodes = &self.schema.nodes;
+        for &i in schemas {
+            let node = &nodes[i];
+            if let Some(min) = node.min_length {
+                if len < min {
+                    self.report(at, "minLength", format!("{} characters, fewer than {}", len, min));
+                }
+            }
+            if let Some(max) = node.max_length {
+                if len > max {
+                    self.report(at, "maxLength", format!("{} characters, more than {}", len, max));
+                }
+            }
+            #[cfg(feature = "regex")]
+            {
+                if let (Some(regex), Some(s)) = (node.pattern.as_ref(), s) {
+                    if !regex.is_match(s) {
+                        self.report(at, "pattern", format!("does not match `{}`", regex.as_str()));
+                    }
+                }
+            }
+        }
+        if let Some(s) = s {
+            if schemas.iter().any(|&i| nodes[i].captures()) {
+                self.check_value(schemas, &Value::String(s.to_string()), at);
+            }
+        }
+    }
+
+    fn check_number(&mut self, schemas: &[usize], n: N, at: Position) {
+        let nodes = &self.schema.nodes;
+        for &s in schemas {
+            let node = &nodes[s];
+            let limits = [(node.minimum, "minimum", "less than"),
+                          (node.maximum, "maximum", "greater than"),
+                          (node.exclusive_minimum, "exclusiveMinimum", "not greater than"),
+                          (node.exclusive_maximum, "exclusiveMaximum", "not less than")];
+            for &(limit, keyword, relation) in &limits {
+                let limit = match limit {
+                    Some(limit) => limit,
+                    None => continue,
+                };
+                let order = value::compare(n, limit);
+                let fails = match keyword {
+                    "minimum" => order == Some(Ordering::Less),
+                    "maximum" => order == Some(Ordering::Greater),
+                    "exclusiveMinimum" => order != Some(Ordering::Greater),
+                    _ => order != Some(Ordering::Less),
+                };
+                if fails {
+                    let message = format!("{} is {} {}", show(n), relation, show(limit));
+                    self.report(at, keyword, message);
+                }
+            }
+        }
+    }
+
+    /// Checks `enum` and `const` against a whole value.
+    fn check_value(&mut self, schemas: &[usize], v: &Value, at: Position) {
+        let nodes = &self.schema.nodes;
+        for &s in schemas {
+            let node = &nodes[s];
+            if let Some(ref values) = node.one_of {
+                if !values.contains(v) {
+                    self.report(at, "enum", "not one of the allowed values".into());
+                }
+            }
+            if let Some(ref constant) = node.constant {
+                if v != constant {
+                    self.report(at, "const", "not equal to the constant".into());
+                }
+            }
+        }
+    }
+
+    fn report(&mut self, at: Position, keyword: &'static str, message: String) {
+        self.violations.push(Violation {
+            pointer: self.tracker.pointer(),
+            position: at,
+            keyword,
+            message,
+        });
+    }
+}
+
+/// The names of the types in `types`, joined with "or".
+fn type_names(types: u8) -> String {
+    let names: Vec<&str> = TYPES.iter().filter(|t| types & t.1 != 0).map(|t| t.0).collect();
+    names.join(" or ")
+}
+
+fn show(n: N) -> String {
+    match n {
+        N::Int(i) => i.to_string(),
+        N::Uint(u) => u.to_string(),
+        N::Float(f) => format!("{:?}", f),
+    }
+}
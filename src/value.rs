@@ -0,0 +1,266 @@
+//! An in-memory JSON value, for the small documents and subtrees that are
+//! easier to work with whole, like schemas.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::mem;
+
+use pull::Pull;
+use writer::JsonWriter;
+use {Block, Event, Parser, Result, N};
+
+/// A JSON value. Object members keep their order, and equality follows
+/// JSON: numbers compare by value, so `1` equals `1.0`, and objects compare
+/// regardless of the order of their members.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(N),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Parses a single JSON document.
+    pub fn parse(json: &str) -> Result<Value> {
+        let mut p = Parser::from_reader(json.as_bytes());
+        Value::pull(&mut p)
+    }
+
+    /// The member `key` of an object. With duplicate keys, the last one.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref members) => {
+                members.iter().rev().find(|m| m.0 == key).map(|m| &m.1)
+            }
+            _ => None,
+        }
+    }
+
+    /// The value at a JSON Pointer, like `/items/0`.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        let mut value = self;
+        for token in ::path::parse_pointer(pointer).ok()? {
+            value = match *value {
+                Value::Object(_) => value.get(&token)?,
+                Value::Array(ref elements) => elements.get(token.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Number(N::Float(f)) => Some(f),
+            Value::Number(N::Int(i)) => Some(i as f64),
+            Value::Number(N::Uint(u)) => Some(u as f64),
+            _ => None,
+        }
+    }
+
+    /// A non-negative integer, including floats without a fraction.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Number(N::Uint(u)) => Some(u),
+            Value::Number(N::Int(i)) if i >= 0 => Some(i as u64),
+            Value::Number(N::Float(f)) if f >= 0.0 && f.fract() == 0.0 && f < u64::MAX as f64 => {
+                Some(f as u64)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match *self {
+            Value::Array(ref elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Value)]> {
+        match *self {
+            Value::Object(ref members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// Writes the value as events.
+    pub fn write<W: Write>(&self, w: &mut JsonWriter<W>) -> Result<()> {
+        match *self {
+            Value::Null => w.write_event(&Event::Null),
+            Value::Bool(b) => w.write_event(&Event::Bool(b)),
+            Value::Number(n) => w.write_event(&Event::Number(n)),
+            Value::String(ref s) => w.write_event(&Event::String(s.clone())),
+            Value::Array(ref elements) => {
+                w.write_event(&Event::Start(Block::Array))?;
+                for element in elements {
+                    element.write(w)?;
+                }
+                w.write_event(&Event::End(Block::Array))
+            }
+            Value::Object(ref members) => {
+                w.write_event(&Event::Start(Block::Object))?;
+                for (key, value) in members {
+                    w.write_event(&Event::Key(key.clone()))?;
+                    value.write(w)?;
+                }
+                w.write_event(&Event::End(Block::Object))
+            }
+        }
+    }
+}
+
+/// Compact JSON.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut w = JsonWriter::new(Vec::new());
+        self.write(&mut w).map_err(|_| fmt::Error)?;
+        f.write_str(&String::from_utf8_lossy(&w.into_inner()))
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => compare(*a, *b) == Some(Ordering::Equal),
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Object(a), Value::Object(b)) => {
+                a.iter().all(|m| other.get(&m.0).is_some() && self.get(&m.0) == other.get(&m.0)) &&
+                b.iter().all(|m| self.get(&m.0).is_some())
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Pull for Value {
+    fn pull<B: BufRead>(parser: &mut Parser<B>) -> Result<Value> {
+        let mut builder = ValueBuilder::new();
+        for ev in parser.by_ref() {
+            if let Some(value) = builder.event(ev?) {
+                return Ok(value);
+            }
+        }
+        Err("EOF".into())
+    }
+}
+
+/// Assembles values from events.
+#[derive(Debug, Default)]
+pub struct ValueBuilder {
+    /// Open containers, each with the key it is a member under.
+    stack: Vec<(Option<String>, Value)>,
+    key: Option<String>,
+    chunks: Option<String>,
+}
+
+impl ValueBuilder {
+    pub fn new() -> ValueBuilder {
+        ValueBuilder::default()
+    }
+
+    /// Adds an event, returning the value once a top-level value is
+    /// complete.
+    pub fn event(&mut self, ev: Event) -> Option<Value> {
+        let value = match ev {
+            Event::Start(block) => {
+                let container = match block {
+                    Block::Object => Value::Object(Vec::new()),
+                    Block::Array => Value::Array(Vec::new()),
+                };
+                self.stack.push((self.key.take(), container));
+                return None;
+            }
+            Event::End(_) => {
+                let (key, container) = self.stack.pop()?;
+                self.key = key;
+                container
+            }
+            Event::Key(k) => {
+                self.key = Some(k);
+                return None;
+            }
+            Event::InternedKey(k) => {
+                self.key = Some(k.to_string());
+                return None;
+            }
+            Event::RawKey(k) => {
+                self.key = Some(String::from_utf8_lossy(&k).into_owned());
+                return None;
+            }
+            Event::String(s) => Value::String(s),
+            Event::Bytes(b) => Value::String(String::from_utf8_lossy(&b).into_owned()),
+            Event::StringChunk(s) => {
+                self.chunks.get_or_insert_with(String::new).push_str(&s);
+                return None;
+            }
+            Event::StringEnd => Value::String(self.chunks.take().unwrap_or_default()),
+            Event::Number(n) => Value::Number(n),
+            Event::Bool(b) => Value::Bool(b),
+            Event::Null => Value::Null,
+        };
+        match self.stack.last_mut() {
+            Some(&mut (_, Value::Object(ref mut members))) => {
+                let key = mem::take(&mut self.key).unwrap_or_default();
+                members.push((key, value));
+                None
+            }
+            Some(&mut (_, Value::Array(ref mut elements))) => {
+                elements.push(value);
+                None
+            }
+            _ => Some(value),
+        }
+    }
+}
+
+/// Reads the value that starts with `first`.
+pub(crate) fn read_value<B: BufRead>(p: &mut Parser<B>, first: Event) -> Result<Value> {
+    let mut builder = ValueBuilder::new();
+    let mut value = None;
+    p.read_value_with(first, |ev| {
+        value = builder.event(ev);
+        Ok(())
+    })?;
+    value.ok_or_else(|| "incomplete value".into())
+}
+
+/// Compares numbers by value, exactly for integers.
+pub(crate) fn compare(a: N, b: N) -> Option<Ordering> {
+    match (a, b) {
+        (N::Int(a), N::Int(b)) => Some(a.cmp(&b)),
+        (N::Uint(a), N::Uint(b)) => Some(a.cmp(&b)),
+        (N::Int(a), N::Uint(b)) => Some((a as i128).cmp(&(b as i128))),
+        (N::Uint(a), N::Int(b)) => Some((a as i128).cmp(&(b as i128))),
+        (a, b) => to_f64(a).partial_cmp(&to_f64(b)),
+    }
+}
+
+fn to_f64(n: N) -> f64 {
+    match n {
+        N::Float(f) => f,
+        N::Int(i) => i as f64,
+        N::Uint(u) => u as f64,
+    }
+}
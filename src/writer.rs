@@ -0,0 +1,214 @@
+//! Writing an event stream back out as JSON text.
+
+use std::io::Write;
+use std::mem;
+
+use path::{Shape, Step};
+use {Block, Event, N, Result};
+
+#[derive(Debug)]
+struct Frame {
+    block: Block,
+    empty: bool,
+}
+
+/// Writes events as JSON, compact or indented.
+///
+/// Top-level values are separated by newlines, so a sequence of them comes
+/// out as NDJSON when written compactly. Strings and keys that are not valid
+/// UTF-8 are written with U+FFFD in place of the invalid bytes.
+#[derive(Debug)]
+pub struct JsonWriter<W: Write> {
+    w: W,
+    indent: Option<usize>,
+    frames: Vec<Frame>,
+    after_key: bool,
+    shape: Shape,
+    values: usize,
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(w: W) -> JsonWriter<W> {
+        JsonWriter {
+            w,
+            indent: None,
+            frames: Vec::new(),
+            after_key: false,
+            shape: Shape::new(),
+            values: 0,
+        }
+    }
+
+    /// Put every member and element on its own line, indented by `width`
+    /// spaces per level.
+    pub fn indent(mut self, width: usize) -> JsonWriter<W> {
+        self.indent = Some(width);
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    /// Writes every event of `events`, stopping at the first error.
+    pub fn write_events<I>(&mut self, events: I) -> Result<()>
+        where I: IntoIterator<Item = Result<Event>>
+    {
+        for ev in events {
+            self.write_event(&ev?)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_event(&mut self, ev: &Event) -> Result<()> {
+        let step = self.shape.step(ev);
+        match *ev {
+            Event::Start(block) => {
+                self.before_value()?;
+                self.w.write_all(if block == Block::Object { b"{" } else { b"[" })?;
+                self.frames.push(Frame {
+                    block,
+                    empty: true,
+                });
+            }
+            Event::End(block) => {
+                let empty = match self.frames.pop() {
+                    Some(ref frame) if frame.block == block => frame.empty,
+                    _ => return Err("unbalanced end of container".into()),
+                };
+                if !empty {
+                    self.newline()?;
+                }
+                self.w.write_all(if block == Block::Object { b"}" } else { b"]" })?;
+                self.after_value();
+            }
+            Event::Key(ref k) => self.write_key(k)?,
+            Event::InternedKey(ref k) => self.write_key(k)?,
+            Event::RawKey(ref k) => self.write_key(&String::from_utf8_lossy(k))?,
+            Event::String(ref s) => self.write_scalar(|w| write_string(w, s))?,
+            Event::Bytes(ref b) => {
+                self.write_scalar(|w| write_string(w, &String::from_utf8_lossy(b)))?
+            }
+            Event::StringChunk(ref s) => {
+                if step == Step::Open {
+                    self.before_value()?;
+                    self.w.write_all(b"\"")?;
+                }
+                write_escaped(&mut self.w, s)?;
+            }
+            Event::StringEnd => {
+                self.w.write_all(b"\"")?;
+                self.after_value();
+            }
+            Event::Number(n) => self.write_scalar(|w| write_number(w, n))?,
+            Event::Bool(b) => {
+                self.write_scalar(|w| w.write_all(if b { b"true" } else { b"false" }))?
+            }
+            Event::Null => self.write_scalar(|w| w.write_all(b"null"))?,
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.w.flush()?;
+        Ok(())
+    }
+
+    fn write_key(&mut self, k: &str) -> Result<()> {
+        self.before_value()?;
+        write_string(&mut self.w, k)?;
+        self.w.write_all(if self.indent.is_some() { b": " } else { b":" })?;
+        self.after_key = true;
+        Ok(())
+    }
+
+    fn write_scalar<F>(&mut self, f: F) -> Result<()>
+        where F: FnOnce(&mut W) -> ::std::io::Result<()>
+    {
+        self.before_value()?;
+        f(&mut self.w)?;
+        self.after_value();
+        Ok(())
+    }
+
+    /// Writes the separator and indentation before a key or value.
+    fn before_value(&mut self) -> Result<()> {
+        if self.after_key {
+            self.after_key = false;
+            return Ok(());
+        }
+        let first = match self.frames.last_mut() {
+            Some(frame) => mem::replace(&mut frame.empty, false),
+            None => {
+                if self.values > 0 {
+                    self.w.write_all(b"\n")?;
+                }
+                return Ok(());
+            }
+        };
+        if !first {
+            self.w.write_all(b",")?;
+        }
+        self.newline()
+    }
+
+    fn after_value(&mut self) {
+        if self.frames.is_empty() {
+            self.values += 1;
+        }
+    }
+
+    /// Starts a new line indented to the current depth, when indenting.
+    fn newline(&mut self) -> Result<()> {
+        if let Some(width) = self.indent {
+            self.w.write_all(b"\n")?;
+            for _ in 0..width * self.frames.len() {
+                self.w.write_all(b" ")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_string<W: Write>(w: &mut W, s: &str) -> ::std::io::Result<()> {
+    w.write_all(b"\"")?;
+    write_escaped(w, s)?;
+    w.write_all(b"\"")
+}
+
+/// Writes `s` escaped for use inside a JSON string.
+pub fn write_escaped<W: Write>(w: &mut W, s: &str) -> ::std::io::Result<()> {
+    let bytes = s.as_bytes();
+    let mut start = 0;
+    for (i, &c) in bytes.iter().enumerate() {
+        let escape: &[u8] = match c {
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            0x08 => b"\\b",
+            0x0C => b"\\f",
+            0x00..=0x1F => b"",
+            _ => continue,
+        };
+        w.write_all(&bytes[start..i])?;
+        if escape.is_empty() {
+            write!(w, "\\u{:04x}", c)?;
+        } else {
+            w.write_all(escape)?;
+        }
+        start = i + 1;
+    }
+    w.write_all(&bytes[start..])
+}
+
+fn write_number<W: Write>(w: &mut W, n: N) -> ::std::io::Result<()> {
+    match n {
+        N::Int(n) => write!(w, "{}", n),
+        N::Uint(n) => write!(w, "{}", n),
+        N::Float(f) if f.is_finite() => write!(w, "{:?}", f),
+        // JSON has no infinities or NaN.
+        N::Float(_) => w.write_all(b"null"),
+    }
+}
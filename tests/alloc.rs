@@ -0,0 +1,56 @@
+//! Checks that `Parser::next_ref` does not allocate once warmed up. This is
+//! the only test in its binary so that other tests cannot allocate while the
+//! counter is being watched.
+
+extern crate jsonpull;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use jsonpull::*;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[test]
+fn test_next_ref_does_not_allocate() {
+    let record = r#"{"id": 12345, "name": "some name\twith \"escapes\" é💖",
+                     "tags": ["a", "bb", "ccc"], "score": -1.5e3, "ok": true, "none": null}"#;
+    let input = format!("[{}]", vec![record; 1000].join(",\n"));
+    let mut p = Parser::from_reader(input.as_bytes());
+
+    // The first record grows the scratch buffer and container stack.
+    while p.next_ref().unwrap() != Some(EventRef::End(Block::Object)) {}
+
+    let mut events = 0;
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    while let Some(ev) = p.next_ref().unwrap() {
+        if let EventRef::String(s) = ev {
+            assert!(!s.is_empty());
+        }
+        events += 1;
+    }
+    let after = ALLOCATIONS.load(Ordering::SeqCst);
+    assert!(events > 10_000);
+    assert_eq!(after - before, 0);
+}
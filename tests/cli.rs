@@ -0,0 +1,139 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn run(args: &[&str], stdin: Option<&str>) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_jsonpull"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    {
+        let mut input = child.stdin.take().unwrap();
+        if let Some(s) = stdin {
+            input.write_all(s.as_bytes()).unwrap();
+        }
+    }
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_cli_validate() {
+    let doc = fixture("doc.json");
+    assert_eq!(stdout(&run(&["validate", &doc], None)), "");
+    let out = run(&["validate", &fixture("invalid.json")], None);
+    assert_eq!(out.status.code(), Some(1));
+    let err = String::from_utf8(out.stderr).unwrap();
+    assert!(err.contains("invalid.json:2: offset 19"), "{}", err);
+    assert_eq!(run(&["validate"], Some("1 2")).status.code(), Some(1));
+    assert!(run(&["validate", "-m"], Some("1 2")).status.success());
+    let cases = [("", "1: offset 0: unexpected EOF"),
+                 ("[1", "1: offset 2: unexpected EOF"),
+                 ("{\"a\": [1, 2", "1: offset 11: unexpected EOF"),
+                 ("{\"a\"}", "1: offset 5: expected 'Colon', found '}'"),
+                 ("{\"a\":}", "1: offset 6: expected 'Value', found '}'"),
+                 ("1,", "1: offset 2: expected 'Value', found ','")];
+    for &(input, message) in &cases {
+        let out = run(&["validate"], Some(input));
+        assert_eq!(out.status.code(), Some(1), "{:?}", input);
+        assert_eq!(String::from_utf8(out.stderr).unwrap(),
+                   format!("jsonpull: <stdin>:{}\n", message));
+    }
+    assert!(run(&["validate", "-m"], Some("")).status.success());
+
+    let out = run(&["validate", "-W"], Some("{\"a\": [1,],\n \"a\": 2}"));
+    assert_eq!(stdout(&out), "");
+    assert_eq!(String::from_utf8(out.stderr).unwrap(),
+               "jsonpull: <stdin>: warning: trailing comma at line 1, offset 9\n\
+                jsonpull: <stdin>: warning: duplicate key \"a\" at line 2, offset 13\n");
+}
+
+#[test]
+fn test_cli_fmt_minify() {
+    let doc = fixture("doc.json");
+    let minified = stdout(&run(&["minify", &doc], None)).to_string();
+    assert_eq!(minified,
+               "{\"name\":\"jsonpull\",\"version\":[0,1,0],\"authors\":[{\"name\":\"a\",\
+                \"email\":null},{\"name\":\"b/c\",\"tags\":{}}],\"ratio\":0.25,\"stable\":false}\n");
+    let pretty = stdout(&run(&["fmt", "--indent", "4", &doc], None)).to_string();
+    assert!(pretty.starts_with("{\n    \"name\": \"jsonpull\",\n    \"version\": [\n        0,"));
+    assert_eq!(stdout(&run(&["minify", "-"], Some(&pretty))), minified);
+    assert_eq!(stdout(&run(&["minify", "-m", &fixture("records.ndjson")], None)),
+               "{\"id\":1,\"ok\":true}\n{\"id\":2,\"ok\":false}\n");
+}
+
+#[test]
+fn test_cli_get() {
+    let doc = fixture("doc.json");
+    assert_eq!(stdout(&run(&["get", "/authors/1/name", &doc], None)), "\"b/c\"\n");
+    assert_eq!(stdout(&run(&["get", "/authors/0", &doc], None)),
+               "{\"name\":\"a\",\"email\":null}\n");
+    assert_eq!(stdout(&run(&["get", "", &doc], None)).len(),
+               stdout(&run(&["minify", &doc], None)).len());
+    assert_eq!(run(&["get", "/missing", &doc], None).status.code(), Some(1));
+    assert_eq!(run(&["get"], None).status.code(), Some(2));
+}
+
+#[test]
+fn test_cli_stats() {
+    let out = run(&["stats", &fixture("doc.json")], None);
+    let out = stdout(&out);
+    for line in &["objects: 4", "arrays: 2", "keys: 9", "strings: 3", "numbers: 4",
+                  "booleans: 1", "nulls: 1", "max depth: 4"] {
+        assert!(out.lines().any(|l| l == *line), "{} not in {}", line, out);
+    }
+}
+
+#[test]
+fn test_cli_paths() {
+    let doc = fixture("doc.json");
+    let lines = stdout(&run(&["paths", &doc], None)).to_string();
+    assert!(lines.starts_with("json = {};\njson.name = \"jsonpull\";\njson.version = [];\n"));
+    assert!(lines.contains("\njson.authors[1].tags = {};\n"));
+    let minified = stdout(&run(&["minify", &doc], None)).to_string();
+    let rebuilt = stdout(&run(&["unpaths"], Some(&lines))).to_string();
+    assert_eq!(stdout(&run(&["minify"], Some(&rebuilt))), minified);
+
+    let input = "{\"a b\": {\"c\\\"\": [true]}, \"long\": \"".to_string() +
+                &"x\\n".repeat(40000) + "\"}";
+    let lines = stdout(&run(&["paths"], Some(&input))).to_string();
+    assert!(lines.starts_with("json = {};\njson[\"a b\"] = {};\njson[\"a b\"][\"c\\\"\"] = [];\n"));
+    let rebuilt = stdout(&run(&["unpaths"], Some(&lines))).to_string();
+    assert_eq!(stdout(&run(&["minify"], Some(&rebuilt))),
+               stdout(&run(&["minify"], Some(&input))));
+}
+
+#[test]
+fn test_cli_unpaths() {
+    let out = run(&["unpaths", "--indent", "0"],
+                  Some("json.items[2].id = 7;\njson.items[3] = \"x\";\njson.n = 1;\n"));
+    assert_eq!(stdout(&out).replace('\n', ""),
+               "{\"items\": [null,null,{\"id\": 7},\"x\"],\"n\": 1}");
+    let out = run(&["unpaths"], Some("json = 1;\njson = [];\njson[0] = 2;\n"));
+    assert_eq!(stdout(&out), "1\n[\n  2\n]\n");
+    for bad in &["json.a = 1;\njson.a.b = 2;", "json[1] = 1;\njson[0] = 1;",
+                 "json.a = ;", "x.a = 1;", "json = {};\njson[0] = 1;"] {
+        assert_eq!(run(&["unpaths"], Some(bad)).status.code(), Some(1), "{}", bad);
+    }
+}
+
+#[test]
+fn test_cli_csv() {
+    let ndjson = fixture("records.ndjson");
+    assert_eq!(stdout(&run(&["csv", &ndjson], None)), "id,ok\n1,true\n2,false\n");
+    assert_eq!(stdout(&run(&["csv", "--tsv", "--no-header", "--columns", "ok,x", &ndjson], None)),
+               "true\t\nfalse\t\n");
+    let out = run(&["csv", "--infer", "2"], Some("[{\"a\": 1}, {\"b\": {\"c\": \"d\"}}]"));
+    assert_eq!(stdout(&out), "a,b.c\n1,\n,d\n");
+    assert_eq!(run(&["csv"], Some("[1]")).status.code(), Some(1));
+}
@@ -0,0 +1,62 @@
+extern crate jsonpull;
+
+use jsonpull::csv::{CsvWriter, Format};
+use jsonpull::Parser;
+
+fn convert(json: &str, w: CsvWriter<Vec<u8>>) -> (String, u64) {
+    let mut p = Parser::from_reader(json.as_bytes()).multiple_values(true);
+    let mut w = w;
+    let rows = w.write_records(&mut p).unwrap();
+    (String::from_utf8(w.into_inner()).unwrap(), rows)
+}
+
+#[test]
+fn test_csv() {
+    let json = r#"[{"id": 1, "name": "a, b", "meta": {"tags": [1, {"x": 2}], "ok": true}},
+                   {"id": 2.5, "name": "say \"hi\"\n", "extra": 3, "meta": {"ok": null}},
+                   {"meta": {}}]"#;
+    let (csv, rows) = convert(json, CsvWriter::new(Vec::new()));
+    assert_eq!(rows, 3);
+    assert_eq!(csv,
+               "id,name,meta.tags,meta.ok\n\
+                1,\"a, b\",\"[1,{\"\"x\"\":2}]\",true\n\
+                2.5,\"say \"\"hi\"\"\n\",,\n\
+                ,,,\n");
+
+    let (tsv, _) = convert(json, CsvWriter::new(Vec::new()).format(Format::Tsv).infer_columns(3));
+    assert_eq!(tsv,
+               "id\tname\tmeta.tags\tmeta.ok\textra\n\
+                1\ta, b\t[1,{\"x\":2}]\ttrue\t\n\
+                2.5\tsay \"hi\"\\n\t\t\t3\n\
+                \t\t\t\t\n");
+
+    let w = CsvWriter::new(Vec::new()).columns(vec!["extra", "id"]).header(false);
+    assert_eq!(convert(json, w).0, ",1\n3,2.5\n,\n");
+}
+
+#[test]
+fn test_csv_ndjson() {
+    let json = "{\"a\": \"x\\ty\"}\n{\"a\": \"\\\\\", \"b\": false}\n";
+    let (tsv, rows) = convert(json, CsvWriter::new(Vec::new()).format(Format::Tsv));
+    assert_eq!(rows, 2);
+    assert_eq!(tsv, "a\nx\\ty\n\\\\\n");
+    assert_eq!(convert("", CsvWriter::new(Vec::new())), (String::new(), 0));
+    assert_eq!(convert("[]", CsvWriter::new(Vec::new())), (String::new(), 0));
+    assert_eq!(convert("[]", CsvWriter::new(Vec::new()).columns(vec!["a"])),
+               ("a\n".to_string(), 0));
+
+    let long = "x".repeat(100);
+    let json = format!("{{\"s\": \"{}\"}}", long);
+    let mut p = Parser::from_reader(json.as_bytes()).string_chunk_size(16);
+    let mut w = CsvWriter::new(Vec::new());
+    w.write_records(&mut p).unwrap();
+    assert_eq!(String::from_utf8(w.into_inner()).unwrap(), format!("s\n{}\n", long));
+}
+
+#[test]
+fn test_csv_errors() {
+    for json in &["[1]", "[[{}]]", "{\"a\": 1} 2", "{\"a\": [1}"] {
+        let mut p = Parser::from_reader(json.as_bytes()).multiple_values(true);
+        assert!(CsvWriter::new(Vec::new()).write_records(&mut p).is_err(), "{}", json);
+    }
+}
@@ -0,0 +1,90 @@
+extern crate jsonpull;
+
+use jsonpull::diff::{self, Change, Op};
+use jsonpull::value::Value;
+use jsonpull::writer::JsonWriter;
+use jsonpull::Parser;
+
+fn changes(a: &str, b: &str) -> Vec<Change> {
+    let mut a = Parser::from_reader(a.as_bytes()).string_chunk_size(4);
+    let mut b = Parser::from_reader(b.as_bytes());
+    jsonpull::diff(&mut a, &mut b).unwrap()
+}
+
+fn report(a: &str, b: &str) -> Vec<String> {
+    changes(a, b).iter().map(|c| c.to_string()).collect()
+}
+
+fn patch(a: &str, b: &str) -> String {
+    let mut w = JsonWriter::new(Vec::new());
+    diff::write_patch(&changes(a, b), &mut w).unwrap();
+    String::from_utf8(w.into_inner()).unwrap()
+}
+
+#[test]
+fn test_diff_equal() {
+    let doc = r#"{"a": [1, {"b": null}], "c": "a long string", "d": 1.5}"#;
+    assert!(changes(doc, doc).is_empty());
+    // Numbers compare by value.
+    assert!(changes("[1, 2.0, 1e2]", "[1.0, 2, 100]").is_empty());
+}
+
+#[test]
+fn test_diff_scalars_and_types() {
+    assert_eq!(report("1", "2"), ["~ (root): 1 -> 2"]);
+    assert_eq!(report(r#"{"a": [1, 2], "b": "x"}"#, r#"{"a": {"0": 1}, "b": "a long y"}"#),
+               ["~ /a: [1,2] -> {\"0\":1}", "~ /b: \"x\" -> \"a long y\""]);
+    let c = &changes(r#"{"a/b~": true}"#, r#"{"a/b~": false}"#)[0];
+    assert_eq!(c.op, Op::Replace);
+    assert_eq!(c.path, "/a~1b~0");
+    assert_eq!(c.old, Some(Value::Bool(true)));
+    assert_eq!(c.new, Some(Value::Bool(false)));
+}
+
+#[test]
+fn test_diff_arrays() {
+    assert_eq!(report("[1, 2, 3]", "[1, 5]"), ["~ /1: 2 -> 5", "- /2: 3"]);
+    assert_eq!(report("[1, 2, 3]", "[1]"), ["- /1: 2", "- /1: 3"]);
+    assert_eq!(report("[[1], []]", "[[1, [2]], [], 4]"), ["+ /0/1: [2]", "+ /2: 4"]);
+}
+
+#[test]
+fn test_diff_objects() {
+    // Same order, streamed.
+    assert_eq!(report(r#"{"a": 1, "b": {"c": 2}, "d": 3}"#, r#"{"a": 1, "b": {"c": 4}}"#),
+               ["~ /b/c: 2 -> 4", "- /d: 3"]);
+    assert_eq!(report(r#"{"a": 1}"#, r#"{"a": 1, "b": [true]}"#), ["+ /b: [true]"]);
+    // Reordered keys fall back to comparing by key, nested values included.
+    assert_eq!(report(r#"{"id": 1, "x": {"p": [1, 2], "q": 0}, "y": 2, "z": 3}"#,
+                      r#"{"id": 1, "z": 3, "w": 9, "x": {"q": 0, "p": [1]}}"#),
+               ["- /x/p/1: 2", "- /y: 2", "+ /w: 9"]);
+    // With duplicate keys the last one counts.
+    assert!(changes(r#"{"b": 1, "a": 1, "a": 2}"#, r#"{"a": 2, "b": 1}"#).is_empty());
+}
+
+#[test]
+fn test_diff_patch() {
+    assert_eq!(patch(r#"{"a": 1, "b": [1, 2], "c": null}"#, r#"{"a": 2, "b": [1], "d": "x"}"#),
+               concat!(r#"[{"op":"replace","path":"/a","value":2},"#,
+                       r#"{"op":"remove","path":"/b/1"},"#,
+                       r#"{"op":"remove","path":"/c"},"#,
+                       r#"{"op":"add","path":"/d","value":"x"}]"#));
+}
+
+#[test]
+fn test_diff_with_stops() {
+    let mut a = Parser::from_reader(&b"[1, 2, 3, 4]"[..]);
+    let mut b = Parser::from_reader(&b"[5, 6, 7, 8]"[..]);
+    let mut seen = Vec::new();
+    let equal = diff::diff_with(&mut a, &mut b, |c| {
+            seen.push(c.path);
+            seen.len() < 2
+        })
+        .unwrap();
+    assert!(!equal);
+    assert_eq!(seen, ["/0", "/1"]);
+
+    let mut a = Parser::from_reader(&b"[1, 2"[..]);
+    let mut b = Parser::from_reader(&b"[1, 2, 3]"[..]);
+    assert!(diff::diff_with(&mut a, &mut b, |_| true).is_err());
+}
@@ -0,0 +1,89 @@
+extern crate jsonpull;
+
+use std::io::BufReader;
+
+use jsonpull::*;
+use jsonpull::encoding::{Decoder, Encoding};
+
+const DOC: &str = "{\"k\u{e9}y\": [\"\u{1F496}\", 12, null]}";
+
+fn expected() -> Vec<Event> {
+    Parser::from_reader(DOC.as_bytes()).collect::<Result<Vec<_>>>().unwrap()
+}
+
+fn encode(s: &str, enc: Encoding, bom: bool) -> Vec<u8> {
+    let mut out = Vec::new();
+    let text = if bom { format!("\u{FEFF}{}", s) } else { s.to_string() };
+    match enc {
+        Encoding::Utf8 => out.extend_from_slice(text.as_bytes()),
+        Encoding::Utf16Be => {
+            for u in text.encode_utf16() {
+                out.extend_from_slice(&u.to_be_bytes());
+            }
+        }
+        Encoding::Utf16Le => {
+            for u in text.encode_utf16() {
+                out.extend_from_slice(&u.to_le_bytes());
+            }
+        }
+        Encoding::Utf32Be => {
+            for c in text.chars() {
+                out.extend_from_slice(&(c as u32).to_be_bytes());
+            }
+        }
+        Encoding::Utf32Le => {
+            for c in text.chars() {
+                out.extend_from_slice(&(c as u32).to_le_bytes());
+            }
+        }
+    }
+    out
+}
+
+#[test]
+fn test_utf8_bom() {
+    let input = encode(DOC, Encoding::Utf8, true);
+    assert_eq!(Parser::from_reader(&input[..]).collect::<Result<Vec<_>>>().unwrap(),
+               expected());
+    assert!(Parser::from_reader(&b"\xEF\xBB"[..]).next().unwrap().is_err());
+}
+
+#[test]
+fn test_detect_encoding() {
+    for &enc in &[Encoding::Utf8,
+                  Encoding::Utf16Be,
+                  Encoding::Utf16Le,
+                  Encoding::Utf32Be,
+                  Encoding::Utf32Le] {
+        for &bom in &[false, true] {
+            let input = encode(DOC, enc, bom);
+            let decoder = Decoder::new(&input[..]).unwrap();
+            assert_eq!(decoder.encoding(), enc);
+            assert_eq!(Parser::from_reader(decoder).collect::<Result<Vec<_>>>().unwrap(),
+                       expected());
+
+            let small = BufReader::with_capacity(3, &input[..]);
+            let events = Parser::from_reader_detect_encoding(small)
+                .unwrap()
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(events, expected());
+        }
+    }
+}
+
+#[test]
+fn test_detect_encoding_invalid() {
+    // Unpaired high surrogate at the end of UTF-16LE input.
+    let mut input = encode("\"a\"", Encoding::Utf16Le, false);
+    input.extend_from_slice(&[0x00, 0xD8]);
+    let mut p = Parser::from_reader_detect_encoding(&input[..]).unwrap();
+    assert!(p.next().unwrap().is_ok());
+    assert!(p.next().unwrap().is_err());
+}
+
+#[test]
+fn test_invalid_start_byte() {
+    assert!(Parser::from_reader("@".as_bytes()).next().unwrap().is_err());
+    assert!(Parser::from_reader("[1, x]".as_bytes()).nth(2).unwrap().is_err());
+}
@@ -0,0 +1,78 @@
+extern crate jsonpull;
+
+use jsonpull::*;
+
+const DOC: &str = r#"{"skip": {"x": [1, [2]], "y": "z"}, "ids": [1, 2, 3],
+                      "nested": [{"a": 1, "b": 2}, {"b": 3}], "peeked": [4], "last": true}"#;
+
+#[test]
+fn test_object_entries() {
+    let mut p = Parser::from_reader(DOC.as_bytes());
+    let mut keys = Vec::new();
+    let mut ids = Vec::new();
+    let mut bs = Vec::new();
+    {
+        let mut entries = p.object_entries().unwrap();
+        while let Some((key, mut value)) = entries.next_entry().unwrap() {
+            match key.as_str() {
+                "ids" => {
+                    let mut elements = value.array_elements().unwrap();
+                    while let Some(mut element) = elements.next_element().unwrap() {
+                        ids.push(element.read_u64().unwrap());
+                    }
+                }
+                "nested" => {
+                    let mut elements = value.array_elements().unwrap();
+                    while let Some(mut element) = elements.next_element().unwrap() {
+                        let mut members = element.object_entries().unwrap();
+                        while let Some((key, mut value)) = members.next_entry().unwrap() {
+                            if key == "b" {
+                                bs.push(value.read_u64().unwrap());
+                            }
+                        }
+                    }
+                }
+                "peeked" => {
+                    assert_eq!(value.peek_kind().unwrap(), Some(Kind::Array));
+                }
+                "last" => assert_eq!(value.peek().unwrap(), Some(&Event::Bool(true))),
+                _ => (),
+            }
+            keys.push(key);
+        }
+    }
+    assert_eq!(keys, vec!["skip", "ids", "nested", "peeked", "last"]);
+    assert_eq!(ids, vec![1, 2, 3]);
+    assert_eq!(bs, vec![2, 3]);
+    assert!(p.next().is_none());
+}
+
+#[test]
+fn test_entries_dropped_early() {
+    let mut p = Parser::from_reader(r#"[{"a": [1, 2], "b": 3}, [4, 5], 6]"#.as_bytes());
+    let mut elements = p.array_elements().unwrap();
+    {
+        let mut first = elements.next_element().unwrap().unwrap();
+        let mut entries = first.object_entries().unwrap();
+        let (key, mut value) = entries.next_entry().unwrap().unwrap();
+        assert_eq!(key, "a");
+        value.expect_array_start().unwrap();
+        assert_eq!(value.read_u64().unwrap(), 1);
+    }
+    {
+        let mut second = elements.next_element().unwrap().unwrap();
+        let mut inner = second.array_elements().unwrap();
+        inner.next_element().unwrap().unwrap();
+    }
+    assert_eq!(elements.next_element().unwrap().unwrap().read_u64().unwrap(), 6);
+    assert!(elements.next_element().unwrap().is_none());
+}
+
+#[test]
+fn test_entries_skip_error() {
+    let mut p = Parser::from_reader(r#"{"a": [1 2], "b": 3}"#.as_bytes());
+    let mut entries = p.object_entries().unwrap();
+    entries.next_entry().unwrap().unwrap();
+    assert!(entries.next_entry().is_err());
+    assert!(entries.next_entry().unwrap().is_none());
+}
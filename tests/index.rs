@@ -0,0 +1,75 @@
+extern crate jsonpull;
+
+use std::io::Cursor;
+
+use jsonpull::*;
+use jsonpull::index::{Index, IndexBuilder};
+
+const RECORDS: &str = r#"[{"id": 1, "tags": ["a", "b"]},
+ {"id": 2, "tags": []},
+ "three", 4]"#;
+
+#[test]
+fn test_index_elements() {
+    let mut p = Parser::from_reader(RECORDS.as_bytes());
+    let index = IndexBuilder::new().build(&mut p).unwrap();
+    let pointers: Vec<&str> = index.entries().iter().map(|e| e.pointer.as_str()).collect();
+    assert_eq!(pointers, vec!["", "/0", "/1", "/2", "/3"]);
+    assert_eq!(index.element(2), Some(RECORDS.find("\"three\"").unwrap() as u64));
+
+    let mut p = index.open(Cursor::new(RECORDS), "/1").unwrap().unwrap();
+    assert_eq!(p.by_ref().collect::<Result<Vec<_>>>().unwrap(),
+               vec![Event::Start(Block::Object),
+                    Event::Key("id".into()),
+                    Event::Number(N::Uint(2)),
+                    Event::Key("tags".into()),
+                    Event::Start(Block::Array),
+                    Event::End(Block::Array),
+                    Event::End(Block::Object)]);
+    assert_eq!(p.event_offset(), RECORDS.find("[]}").unwrap() as u64 + 2);
+}
+
+#[test]
+fn test_index_nested() {
+    let mut p = Parser::from_reader(RECORDS.as_bytes());
+    let index = IndexBuilder::new().max_depth(3).build(&mut p).unwrap();
+    assert_eq!(index.get("/0/tags/1"), Some(RECORDS.find("\"b\"").unwrap() as u64));
+
+    let mut p = Parser::from_reader_at(Cursor::new(RECORDS), index.get("/0/tags/1").unwrap())
+        .unwrap();
+    assert_eq!(p.next().unwrap().unwrap(), Event::String("b".into()));
+    assert!(p.next().is_none());
+}
+
+#[test]
+fn test_index_persist() {
+    let mut p = Parser::from_reader(r#"{"a/b": [1, 2], "c~": true}"#.as_bytes());
+    let index = IndexBuilder::new().max_depth(2).build(&mut p).unwrap();
+    let mut buf = Vec::new();
+    index.write_to(&mut buf).unwrap();
+    let loaded = Index::read_from(&buf[..]).unwrap();
+    assert_eq!(loaded.entries(), index.entries());
+    assert_eq!(loaded.get("/a~1b/1"), Some(12));
+    assert_eq!(loaded.get("/c~0"), Some(22));
+}
+
+#[test]
+fn test_index_interned_keys() {
+    let doc = r#"{"abcdefgh": [1], "k": 1}"#;
+    let mut p = Parser::from_reader(doc.as_bytes()).intern_keys(16);
+    let index = IndexBuilder::new().max_depth(2).build(&mut p).unwrap();
+    let pointers: Vec<&str> = index.entries().iter().map(|e| e.pointer.as_str()).collect();
+    assert_eq!(pointers, vec!["", "/abcdefgh", "/abcdefgh/0", "/k"]);
+    assert_eq!(index.get("/k"), Some(doc.find("1}").unwrap() as u64));
+}
+
+#[test]
+fn test_index_chunked_strings() {
+    let doc = r#"["abcdefghijkl", "xyz"]"#;
+    let mut p = Parser::from_reader(doc.as_bytes()).string_chunk_size(4);
+    let index = IndexBuilder::new().build(&mut p).unwrap();
+    let pointers: Vec<&str> = index.entries().iter().map(|e| e.pointer.as_str()).collect();
+    assert_eq!(pointers, vec!["", "/0", "/1"]);
+    assert_eq!(index.element(0), Some(1));
+    assert_eq!(index.element(1), Some(doc.find("\"xyz").unwrap() as u64));
+}
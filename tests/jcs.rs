@@ -0,0 +1,99 @@
+extern crate jsonpull;
+
+use jsonpull::jcs::{self, Canonicalizer};
+use jsonpull::Parser;
+
+#[test]
+fn test_jcs_rfc_example() {
+    // RFC 8785, section 3.2.2.
+    let input = r#"{
+        "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+        "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+        "literals": [null, true, false]
+    }"#;
+    assert_eq!(jcs::to_canonical_string(input).unwrap(),
+               "{\"literals\":[null,true,false],\
+                \"numbers\":[333333333.3333333,1e+30,4.5,0.002,1e-27],\
+                \"string\":\"€$\\u000f\\nA'B\\\"\\\\\\\\\\\"/\"}");
+}
+
+#[test]
+fn test_jcs_key_order() {
+    // RFC 8785, section 3.2.3.
+    let input = r#"{
+        "\u20ac": "Euro Sign",
+        "\r": "Carriage Return",
+        "\ufb33": "Hebrew Letter Dalet With Dagesh",
+        "1": "One",
+        "\ud83d\ude00": "Emoji: Grinning Face",
+        "\u0080": "Control",
+        "\u00f6": "Latin Small Letter O With Diaeresis"
+    }"#;
+    assert_eq!(jcs::to_canonical_string(input).unwrap(),
+               "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\
+                \"ö\":\"Latin Small Letter O With Diaeresis\",\"€\":\"Euro Sign\",\
+                \"😀\":\"Emoji: Grinning Face\",\
+                \"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}");
+}
+
+#[test]
+fn test_jcs_numbers() {
+    // RFC 8785, appendix B.
+    let cases: &[(u64, &str)] = &[(0x0000000000000000, "0"),
+                                  (0x8000000000000000, "0"),
+                                  (0x0000000000000001, "5e-324"),
+                                  (0x8000000000000001, "-5e-324"),
+                                  (0x7fefffffffffffff, "1.7976931348623157e+308"),
+                                  (0xffefffffffffffff, "-1.7976931348623157e+308"),
+                                  (0x4340000000000000, "9007199254740992"),
+                                  (0xc340000000000000, "-9007199254740992"),
+                                  (0x4430000000000000, "295147905179352830000"),
+                                  (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+                                  (0x44b52d02c7e14af6, "1e+23"),
+                                  (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+                                  (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+                                  (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+                                  (0x444b1ae4d6e2ef50, "1e+21"),
+                                  (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+                                  (0x3eb0c6f7a0b5ed8d, "0.000001"),
+                                  (0x41b3de4355555553, "333333333.3333332"),
+                                  (0x41b3de4355555554, "333333333.33333325"),
+                                  (0x41b3de4355555555, "333333333.3333333"),
+                                  (0x41b3de4355555556, "333333333.3333334"),
+                                  (0x41b3de4355555557, "333333333.33333343"),
+                                  (0xbecbf647612f3696, "-0.0000033333333333333333"),
+                                  (0x43143ff3c1cb0959, "1424953923781206.2")];
+    for &(bits, expected) in cases {
+        let f = f64::from_bits(bits);
+        assert_eq!(jcs::format_number(f).unwrap(), expected);
+        // The shortest form reads back as the same double.
+        let json = format!("[{}]", expected);
+        assert_eq!(jcs::to_canonical_string(&json).unwrap(), json);
+    }
+    assert!(jcs::format_number(f64::from_bits(0x7fffffffffffffff)).is_err());
+    assert!(jcs::format_number(f64::from_bits(0x7ff0000000000000)).is_err());
+
+    assert_eq!(jcs::to_canonical_string("[-0.0, 1.5e1, 18446744073709551615, -9223372036854775808, \
+                                          123456789012345678901234567890, 1e400]")
+                   .map_err(|e| e.to_string()),
+               Err("NaN and infinities cannot be written as JSON".to_string()));
+    assert_eq!(jcs::to_canonical_string("[-0.0, 1.5e1, 18446744073709551615, -9223372036854775808, \
+                                          123456789012345678901234567890]")
+                   .unwrap(),
+               "[0,15,18446744073709552000,-9223372036854776000,1.2345678901234568e+29]");
+}
+
+#[test]
+fn test_jcs_nesting() {
+    let input = r#"{"b": [{"z": 1, "y": {"d": [], "c": {}}}, "s"], "a": {"x": [true]}}
+                   [{"k": null}] "top""#;
+    let mut p = Parser::from_reader(input.as_bytes()).multiple_values(true).string_chunk_size(4);
+    let out = jcs::canonicalize(&mut p, Vec::new()).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(),
+               "{\"a\":{\"x\":[true]},\"b\":[{\"y\":{\"c\":{},\"d\":[]},\"z\":1},\"s\"]}\n\
+                [{\"k\":null}]\n\"top\"");
+
+    assert!(jcs::to_canonical_string(r#"{"a": 1, "b": 2, "a": 3}"#).is_err());
+    let mut c = Canonicalizer::new(Vec::new());
+    assert!(c.write_event(&jsonpull::Event::Bytes(vec![0xff])).is_err());
+}
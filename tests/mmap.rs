@@ -0,0 +1,39 @@
+#![cfg(feature = "mmap")]
+
+extern crate jsonpull;
+
+use std::fs;
+use std::io::Write;
+
+use jsonpull::*;
+use jsonpull::index::IndexBuilder;
+
+fn temp_file(name: &str, content: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("jsonpull-{}-{}", std::process::id(), name));
+    fs::File::create(&path).unwrap().write_all(content.as_bytes()).unwrap();
+    path
+}
+
+#[test]
+fn test_from_path() {
+    let doc = r#"{"a": ["x", 1], "b": null}"#;
+    let path = temp_file("from_path.json", doc);
+    let events = Parser::from_path(&path).unwrap().collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(events,
+               Parser::from_reader(doc.as_bytes()).collect::<Result<Vec<_>>>().unwrap());
+
+    let index = IndexBuilder::new().build(&mut Parser::from_path(&path).unwrap()).unwrap();
+    let file = fs::File::open(&path).unwrap();
+    let mmap = unsafe { Mmap::map(&file).unwrap() };
+    let mut p = index.open(std::io::Cursor::new(mmap), "/b").unwrap().unwrap();
+    assert_eq!(p.next().unwrap().unwrap(), Event::Null);
+
+    let mmap = unsafe { Mmap::map(&file).unwrap() };
+    assert_eq!(Parser::from_mmap(mmap).count(), events.len());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_from_path_missing() {
+    assert!(Parser::from_path("/nonexistent/jsonpull.json").is_err());
+}
@@ -0,0 +1,121 @@
+extern crate jsonpull;
+
+use jsonpull::*;
+use jsonpull::parallel;
+
+fn record(i: usize) -> String {
+    match i % 4 {
+        0 => format!(r#"{{"id": {}, "name": "rec\"{}", "tags": ["x", {{"y": null}}]}}"#, i, i),
+        1 => format!("[{}, -{}.5, true]", i, i),
+        2 => format!(r#""s,]{}""#, i),
+        _ => "false".to_string(),
+    }
+}
+
+/// Splits a sequential event stream into the top-level values it contains.
+fn sequential(p: Parser<&[u8]>, skip_outer: bool) -> Vec<Vec<Event>> {
+    let mut events = p.collect::<Result<Vec<_>>>().unwrap();
+    if skip_outer {
+        events.remove(0);
+        events.pop();
+    }
+    let mut values = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0;
+    for ev in events {
+        match ev {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => (),
+        }
+        current.push(ev);
+        if depth == 0 {
+            values.push(current);
+            current = Vec::new();
+        }
+    }
+    values
+}
+
+#[test]
+fn test_parallel_array() {
+    let records: Vec<String> = (0..1000).map(record).collect();
+    let input = format!("[{}]", records.join(",\n "));
+    let expected = sequential(Parser::from_reader(input.as_bytes()), true);
+    for threads in 1..5 {
+        let parsed = parallel::parse_array(input.as_bytes(), threads).unwrap();
+        let parsed: Vec<_> = parsed.into_iter().map(|r| r.unwrap()).collect();
+        assert_eq!(parsed, expected);
+    }
+}
+
+#[test]
+fn test_parallel_ndjson() {
+    let records: Vec<String> = (0..1000).map(record).collect();
+    let input = records.join("\n") + "\n\n";
+    let expected = sequential(Parser::from_reader(input.as_bytes()).multiple_values(true),
+                              false);
+    let parsed = parallel::parse_ndjson(input.as_bytes(), 4);
+    let parsed: Vec<_> = parsed.into_iter().map(|r| r.unwrap()).collect();
+    assert_eq!(parsed, expected);
+}
+
+#[test]
+fn test_parallel_malformed_records() {
+    let parsed = parallel::parse_ndjson(b"{\"a\":1},\n2\n", 2);
+    assert!(parsed[0].is_err());
+    assert_eq!(parsed[1].as_ref().unwrap(), &vec![Event::Number(N::Uint(2))]);
+
+    // Truncated records, each cut off inside a different value.
+    let parsed = parallel::parse_ndjson(b"{\"a\":1\n[2\n\"x\n{\"a\"\n4\n", 3);
+    assert_eq!(parsed.len(), 5);
+    assert!(parsed[..4].iter().all(|r| r.is_err()));
+    assert!(parsed[4].is_ok());
+    let parsed = parallel::parse_array(b"[{\"a\": 1}, [1, 2}, 3]", 2).unwrap();
+    assert!(parsed[0].is_ok() && parsed[1].is_err() && parsed[2].is_ok());
+
+    // A panic loses the results of the thread it happened on, not the rest.
+    let records = parallel::split_lines(b"1\n2\n3\n4\n");
+    let parsed = parallel::map_records(&records, 2, |p| {
+        match p.next().unwrap()? {
+            Event::Number(N::Uint(3)) => panic!("record 3"),
+            ev => Ok(ev),
+        }
+    });
+    assert_eq!(parsed[0].as_ref().unwrap(), &Event::Number(N::Uint(1)));
+    assert_eq!(parsed[1].as_ref().unwrap(), &Event::Number(N::Uint(2)));
+    assert!(parsed[2].is_err() && parsed[3].is_err());
+}
+
+#[test]
+fn test_parallel_errors_in_order() {
+    let input = b"{\"a\": 1}\n{\"a\" 1}\n[2]\n";
+    let parsed = parallel::parse_ndjson(input, 3);
+    assert_eq!(parsed.len(), 3);
+    assert!(parsed[0].is_ok());
+    assert!(parsed[1].is_err());
+    assert_eq!(parsed[2].as_ref().unwrap(),
+               &vec![Event::Start(Block::Array), Event::Number(N::Uint(2)), Event::End(Block::Array)]);
+
+    assert!(parallel::split_array(b"[1,,2]").is_err());
+    assert!(parallel::split_array(b"[1, 2").is_err());
+    assert_eq!(parallel::split_array(b" [ ] ").unwrap(), vec![]);
+    let records = parallel::split_array(b"[1, \"a]\",]").unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1].offset, 3);
+}
+
+#[test]
+fn test_parallel_offsets() {
+    let input = b"[1, {\"k\": true}]";
+    let offsets = parallel::map_records(&parallel::split_array(input).unwrap(), 2, |p| {
+        while let Some(ev) = p.next() {
+            if ev? == Event::Bool(true) {
+                return Ok(Some(p.event_offset()));
+            }
+        }
+        Ok(None)
+    });
+    assert_eq!(offsets.into_iter().map(|r| r.unwrap()).collect::<Vec<_>>(),
+               vec![None, Some(10)]);
+}
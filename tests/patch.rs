@@ -0,0 +1,156 @@
+extern crate jsonpull;
+
+use jsonpull::patch::{self, Patch};
+use jsonpull::value::Value;
+use jsonpull::writer::JsonWriter;
+use jsonpull::{Parser, Result};
+
+/// Applies `patch` as the document streams, checking that it agrees with
+/// applying it in memory.
+fn apply(doc: &str, patch: &str) -> Result<String> {
+    let patch = Patch::parse(patch).unwrap();
+    let mut p = Parser::from_reader(doc.as_bytes()).string_chunk_size(4);
+    let mut w = JsonWriter::new(Vec::new());
+    let streamed = patch.apply(&mut p, &mut w).map(|_| String::from_utf8(w.into_inner()).unwrap());
+    let mut value = Value::parse(doc).unwrap();
+    match patch.apply_to(&mut value) {
+        Ok(()) => assert_eq!(Value::parse(streamed.as_ref().unwrap()).unwrap(), value),
+        Err(_) => assert!(streamed.is_err()),
+    }
+    streamed
+}
+
+#[test]
+fn test_patch_rfc_examples() {
+    // RFC 6902, appendix A.
+    assert_eq!(apply(r#"{"foo": "bar"}"#, r#"[{"op": "add", "path": "/baz", "value": "qux"}]"#)
+                   .unwrap(),
+               r#"{"foo":"bar","baz":"qux"}"#);
+    assert_eq!(apply(r#"{"foo": ["bar", "baz"]}"#,
+                     r#"[{"op": "add", "path": "/foo/1", "value": "qux"}]"#)
+                   .unwrap(),
+               r#"{"foo":["bar","qux","baz"]}"#);
+    assert_eq!(apply(r#"{"baz": "qux", "foo": "bar"}"#, r#"[{"op": "remove", "path": "/baz"}]"#)
+                   .unwrap(),
+               r#"{"foo":"bar"}"#);
+    assert_eq!(apply(r#"{"foo": ["bar", "qux", "baz"]}"#,
+                     r#"[{"op": "remove", "path": "/foo/1"}]"#)
+                   .unwrap(),
+               r#"{"foo":["bar","baz"]}"#);
+    assert_eq!(apply(r#"{"baz": "qux", "foo": "bar"}"#,
+                     r#"[{"op": "replace", "path": "/baz", "value": "boo"}]"#)
+                   .unwrap(),
+               r#"{"baz":"boo","foo":"bar"}"#);
+    assert_eq!(apply(r#"{"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}"#,
+                     r#"[{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]"#)
+                   .unwrap(),
+               r#"{"foo":{"bar":"baz"},"qux":{"corge":"grault","thud":"fred"}}"#);
+    assert_eq!(apply(r#"{"foo": ["all", "grass", "cows", "eat"]}"#,
+                     r#"[{"op": "move", "from": "/foo/1", "path": "/foo/3"}]"#)
+                   .unwrap(),
+               r#"{"foo":["all","cows","eat","grass"]}"#);
+    assert_eq!(apply(r#"{"baz": "qux", "foo": ["a", 2, "c"]}"#,
+                     r#"[{"op": "test", "path": "/baz", "value": "qux"},
+                         {"op": "test", "path": "/foo/1", "value": 2}]"#)
+                   .unwrap(),
+               r#"{"baz":"qux","foo":["a",2,"c"]}"#);
+    assert!(apply(r#"{"baz": "qux"}"#, r#"[{"op": "test", "path": "/baz", "value": "bar"}]"#)
+        .is_err());
+    assert_eq!(apply(r#"{"foo": "bar"}"#,
+                     r#"[{"op": "add", "path": "/child", "value": {"grandchild": {}}}]"#)
+                   .unwrap(),
+               r#"{"foo":"bar","child":{"grandchild":{}}}"#);
+    assert!(apply(r#"{"foo": "bar"}"#, r#"[{"op": "add", "path": "/baz/bat", "value": "qux"}]"#)
+        .is_err());
+    assert!(apply(r#"{"/": 9, "~1": 10}"#, r#"[{"op": "test", "path": "/~01", "value": 10}]"#)
+        .is_ok());
+    assert_eq!(apply(r#"{"foo": ["bar"]}"#,
+                     r#"[{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}]"#)
+                   .unwrap(),
+               r#"{"foo":["bar",["abc","def"]]}"#);
+}
+
+#[test]
+fn test_patch_single_pass() {
+    let doc = r#"{"name": "a long name", "tags": ["x", "y", "z"], "meta": {"v": 1, "old": [1]},
+                  "rest": [{"k": "a long value"}]}"#;
+    let patch = r#"[{"op": "replace", "path": "/meta/v", "value": 2},
+                    {"op": "remove", "path": "/meta/old"},
+                    {"op": "add", "path": "/tags/1", "value": "w"},
+                    {"op": "add", "path": "/meta/new", "value": null},
+                    {"op": "test", "path": "/name", "value": "a long name"},
+                    {"op": "replace", "path": "/rest/0/k", "value": true}]"#;
+    assert_eq!(apply(doc, patch).unwrap(),
+               concat!(r#"{"name":"a long name","tags":["x","w","y","z"],"#,
+                       r#""meta":{"v":2,"new":null},"rest":[{"k":true}]}"#));
+}
+
+#[test]
+fn test_patch_dependent_operations() {
+    // Both removals shift the same array, so the second sees the first.
+    assert_eq!(apply(r#"{"a": [1, 2, 3], "b": 0}"#,
+                     r#"[{"op": "remove", "path": "/a/0"}, {"op": "remove", "path": "/a/0"}]"#)
+                   .unwrap(),
+               r#"{"a":[3],"b":0}"#);
+    assert_eq!(apply(r#"[1, 2, 3]"#,
+                     r#"[{"op": "add", "path": "/0", "value": 0},
+                         {"op": "replace", "path": "/1", "value": 10}]"#)
+                   .unwrap(),
+               "[0,10,2,3]");
+    // A shift moves the element that a later operation reaches into.
+    assert_eq!(apply(r#"{"a": [{"x": 0}, {"x": 1}, {"x": 2}, {"x": 3}]}"#,
+                     r#"[{"op": "add", "path": "/a/0", "value": {}},
+                         {"op": "replace", "path": "/a/3/x", "value": "R"}]"#)
+                   .unwrap(),
+               r#"{"a":[{},{"x":0},{"x":1},{"x":"R"},{"x":3}]}"#);
+    assert!(apply(r#"{"a": [{"x": 0}, {"x": 1}]}"#,
+                  r#"[{"op": "remove", "path": "/a/0"},
+                      {"op": "replace", "path": "/a/1/x", "value": "R"}]"#)
+        .is_err());
+    // A member added and then changed.
+    assert_eq!(apply(r#"{"a": {"x": 1}}"#,
+                     r#"[{"op": "add", "path": "/a/b", "value": {}},
+                         {"op": "add", "path": "/a/b/c", "value": 1},
+                         {"op": "copy", "from": "/a/x", "path": "/a/y"}]"#)
+                   .unwrap(),
+               r#"{"a":{"x":1,"b":{"c":1},"y":1}}"#);
+    assert_eq!(apply(r#"{"a": 1}"#, r#"[{"op": "replace", "path": "", "value": [1]}]"#).unwrap(),
+               "[1]");
+    assert!(apply(r#"{"a": {"b": 1}}"#, r#"[{"op": "move", "from": "/a", "path": "/a/b/c"}]"#)
+        .is_err());
+    assert!(apply(r#"[1]"#, r#"[{"op": "replace", "path": "/1", "value": 2}]"#).is_err());
+    assert!(apply(r#"[1]"#, r#"[{"op": "add", "path": "/01", "value": 2}]"#).is_err());
+    assert!(Patch::parse(r#"[{"op": "frobnicate", "path": "/a"}]"#).is_err());
+    assert!(Patch::parse(r#"[{"op": "add", "path": "/a"}]"#).is_err());
+}
+
+#[test]
+fn test_merge_patch() {
+    // RFC 7396, appendix A.
+    let cases = [(r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+                 (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+                 (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+                 (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+                 (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+                 (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+                 (r#"{"a":{"b":"c"}}"#, r#"{"a":{"b":"d","c":null}}"#, r#"{"a":{"b":"d"}}"#),
+                 (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+                 (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+                 (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+                 (r#"{"a":"foo"}"#, r#"null"#, r#"null"#),
+                 (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+                 (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+                 (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+                 (r#"{}"#, r#"{"a":{"bb":{"ccc":null}}}"#, r#"{"a":{"bb":{}}}"#)];
+    for &(doc, patch, expected) in &cases {
+        let patch = Value::parse(patch).unwrap();
+        let mut p = Parser::from_reader(doc.as_bytes()).string_chunk_size(1);
+        let mut w = JsonWriter::new(Vec::new());
+        patch::merge_patch(&mut p, &patch, &mut w).unwrap();
+        assert_eq!(String::from_utf8(w.into_inner()).unwrap(), expected);
+
+        let mut value = Value::parse(doc).unwrap();
+        patch::merge(&mut value, &patch);
+        assert_eq!(value, Value::parse(expected).unwrap());
+    }
+}
@@ -0,0 +1,84 @@
+extern crate jsonpull;
+
+use std::io::BufReader;
+
+use jsonpull::*;
+use jsonpull::scan::{self, Scanner};
+
+/// Deterministic pseudo-random bytes.
+fn noise(seed: u64, len: usize) -> Vec<u8> {
+    const ALPHABET: &[u8] = b"ab \"\\\t\n\r{}[]:,x\xc3\xa9";
+    let mut x = seed;
+    (0..len)
+        .map(|_| {
+            x = x.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ALPHABET[(x >> 33) as usize % ALPHABET.len()]
+        })
+        .collect()
+}
+
+fn document(seed: u64) -> String {
+    let mut s = String::from("[");
+    for i in 0..40 {
+        let pad = " ".repeat((seed as usize * 7 + i * 13) % 90);
+        let text = "x".repeat((seed as usize * 3 + i * 29) % 150);
+        s.push_str(&format!("{}{{\"k{}\":{}\"{}\\\"{}\\n\\u00e9é\",\n\t\"n\": {}}},",
+                            pad, i, pad, text, text, i));
+    }
+    s.push_str("\"end\"]");
+    s
+}
+
+#[test]
+fn test_scanner_runs() {
+    let scanner = Scanner::new();
+    for seed in 0..50 {
+        let data = noise(seed, 300);
+        let expected = data.iter().position(|&c| c == b'"' || c == b'\\').unwrap_or(data.len());
+        assert_eq!(scanner.string_run(&data), expected);
+        let ws = data.iter()
+            .position(|&c| !b" \t\r\n".contains(&c))
+            .unwrap_or(data.len());
+        assert_eq!(scanner.whitespace_run(&data), ws);
+    }
+    assert_eq!(scanner.whitespace_run(&[b' '; 200]), 200);
+}
+
+#[test]
+fn test_scanner_events_match_scalar() {
+    for seed in 0..20 {
+        let doc = document(seed);
+        let scalar = Parser::from_reader(doc.as_bytes())
+            .scanner(Scanner::scalar())
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        for scanner in scan::available() {
+            let events = Parser::from_reader(doc.as_bytes())
+                .scanner(scanner)
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(events, scalar, "{}", scanner.name());
+            for cap in &[1, 7, 64, 100] {
+                let small = Parser::from_reader(BufReader::with_capacity(*cap, doc.as_bytes()))
+                    .scanner(scanner)
+                    .collect::<Result<Vec<_>>>()
+                    .unwrap();
+                assert_eq!(small, scalar, "{} with capacity {}", scanner.name(), cap);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "simd")]
+#[test]
+fn test_classify_matches_scalar() {
+    let scalar = Scanner::scalar();
+    for scanner in scan::available() {
+        for seed in 0..200 {
+            let data = noise(seed, 64);
+            let mut block = [0; 64];
+            block.copy_from_slice(&data);
+            assert_eq!(scanner.classify(&block), scalar.classify(&block), "{}", scanner.name());
+        }
+    }
+}
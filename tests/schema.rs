@@ -0,0 +1,63 @@
+extern crate jsonpull;
+
+use jsonpull::schema::SchemaInferrer;
+use jsonpull::writer::JsonWriter;
+use jsonpull::Parser;
+
+fn infer(json: &str) -> SchemaInferrer {
+    let mut p = Parser::from_reader(json.as_bytes()).multiple_values(true);
+    let mut inferrer = SchemaInferrer::new();
+    inferrer.infer(&mut p).unwrap();
+    inferrer
+}
+
+#[test]
+fn test_infer_records() {
+    let inferrer = infer(r#"{"id": 7, "note": "héllo", "tags": ["a", "bc"], "score": 1.0}
+                            {"id": -2, "note": null, "tags": [], "score": 0.5}
+                            {"id": 18446744073709551615, "tags": ["xyz"], "score": 2}"#);
+    assert_eq!(inferrer.documents(), 3);
+    assert_eq!(inferrer.schema(),
+               "{\"$schema\":\"https://json-schema.org/draft/2020-12/schema\",\
+                \"type\":\"object\",\
+                \"properties\":{\
+                \"id\":{\"type\":\"integer\",\"minimum\":-2,\"maximum\":18446744073709551615,\
+                \"x-presence\":1.0},\
+                \"note\":{\"type\":[\"string\",\"null\"],\"minLength\":5,\"maxLength\":5,\
+                \"x-presence\":0.6666666666666666},\
+                \"tags\":{\"type\":\"array\",\"minItems\":0,\"maxItems\":2,\
+                \"items\":{\"type\":\"string\",\"minLength\":1,\"maxLength\":3},\
+                \"x-presence\":1.0},\
+                \"score\":{\"type\":\"number\",\"minimum\":0.5,\"maximum\":2,\"x-presence\":1.0}},\
+                \"required\":[\"id\",\"tags\",\"score\"]}");
+}
+
+#[test]
+fn test_infer_mixed() {
+    let inferrer = infer(r#"[{"a": {"b": true}}, {"a": [{}, 1]}, "s", null]"#);
+    let mut w = JsonWriter::new(Vec::new()).indent(1);
+    inferrer.write_schema(&mut w).unwrap();
+    let schema = String::from_utf8(w.into_inner()).unwrap();
+    assert!(schema.starts_with("{\n \"$schema\""));
+    assert_eq!(inferrer.schema(),
+               "{\"$schema\":\"https://json-schema.org/draft/2020-12/schema\",\
+                \"type\":\"array\",\"minItems\":4,\"maxItems\":4,\
+                \"items\":{\"type\":[\"object\",\"string\",\"null\"],\"minLength\":1,\"maxLength\":1,\
+                \"properties\":{\"a\":{\"type\":[\"object\",\"array\"],\"minItems\":2,\"maxItems\":2,\
+                \"items\":{\"type\":[\"object\",\"integer\"],\"minimum\":1,\"maximum\":1},\
+                \"properties\":{\"b\":{\"type\":\"boolean\",\"x-presence\":1.0}},\
+                \"required\":[\"b\"],\"x-presence\":1.0}},\
+                \"required\":[\"a\"]}}");
+
+    assert_eq!(SchemaInferrer::new().schema(),
+               "{\"$schema\":\"https://json-schema.org/draft/2020-12/schema\"}");
+}
+
+#[test]
+fn test_infer_chunked_strings() {
+    let json = format!("[\"{}\", \"ab\"]", "é".repeat(50));
+    let mut p = Parser::from_reader(json.as_bytes()).string_chunk_size(8);
+    let mut inferrer = SchemaInferrer::new();
+    inferrer.infer(&mut p).unwrap();
+    assert!(inferrer.schema().contains("\"items\":{\"type\":\"string\",\"minLength\":2,\"maxLength\":50}"));
+}
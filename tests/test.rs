@@ -135,3 +135,370 @@ fn test_total() {
     assert_eq!(p.next().unwrap().unwrap(), Event::End(Block::Array));
     assert_eq!(p.next().unwrap().unwrap(), Event::End(Block::Object));
 }
+
+#[test]
+fn test_zero_in_array() {
+    let j = "[0, 0.5, -0.5]".as_bytes();
+    assert_eq!(Parser::from_reader(j).collect::<Result<Vec<_>>>().unwrap(),
+               vec![Event::Start(Block::Array),
+                    Event::Number(N::Uint(0)),
+                    Event::Number(N::Float(0.5)),
+                    Event::Number(N::Float(-0.5)),
+                    Event::End(Block::Array)]);
+}
+
+#[test]
+fn test_multiple_values() {
+    let j = "{\"a\": 1}\n[true]\n\"x\" null".as_bytes();
+    assert_eq!(Parser::from_reader(j).multiple_values(true).collect::<Result<Vec<_>>>().unwrap(),
+               vec![Event::Start(Block::Object),
+                    Event::Key("a".into()),
+                    Event::Number(N::Uint(1)),
+                    Event::End(Block::Object),
+                    Event::Start(Block::Array),
+                    Event::Bool(true),
+                    Event::End(Block::Array),
+                    Event::String("x".into()),
+                    Event::Null]);
+    assert!(Parser::from_reader("1 2".as_bytes()).nth(1).unwrap().is_err());
+}
+
+#[test]
+fn test_string_policy() {
+    let j = &b"{\"k\xff\": \"a\xc3b\"}"[..];
+    assert!(Parser::from_reader(j).nth(1).unwrap().is_err());
+    assert_eq!(Parser::from_reader(j)
+                   .string_policy(StringPolicy::Lossy)
+                   .collect::<Result<Vec<_>>>()
+                   .unwrap(),
+               vec![Event::Start(Block::Object),
+                    Event::Key("k\u{FFFD}".into()),
+                    Event::String("a\u{FFFD}b".into()),
+                    Event::End(Block::Object)]);
+    assert_eq!(Parser::from_reader(j)
+                   .string_policy(StringPolicy::Raw)
+                   .collect::<Result<Vec<_>>>()
+                   .unwrap(),
+               vec![Event::Start(Block::Object),
+                    Event::RawKey(b"k\xff".to_vec()),
+                    Event::Bytes(b"a\xc3b".to_vec()),
+                    Event::End(Block::Object)]);
+    assert_eq!(Parser::from_reader("\"ok\"".as_bytes())
+                   .string_policy(StringPolicy::Raw)
+                   .next()
+                   .unwrap()
+                   .unwrap(),
+               Event::String("ok".into()));
+
+    // Chunks have no raw form, but their bytes can still be copied out.
+    let j = &b"[\"abcde\xffgh\"]"[..];
+    let chunked = || Parser::from_reader(j).string_policy(StringPolicy::Raw).string_chunk_size(4);
+    let mut p = chunked();
+    assert_eq!(p.nth(1).unwrap().unwrap(), Event::StringChunk("abcd".into()));
+    assert!(p.next().unwrap().is_err());
+    let mut p = chunked();
+    p.nth(1).unwrap().unwrap();
+    assert!(p.next_ref().is_err());
+    let mut p = chunked();
+    let mut out = Vec::new();
+    p.next().unwrap().unwrap();
+    assert_eq!(p.read_string_to(&mut out).unwrap(), 8);
+    assert_eq!(out, b"abcde\xffgh");
+}
+
+#[test]
+fn test_lone_surrogate() {
+    let j = r#"["\uD800", "\uDC00x", "a\uD83Db", "\uD83D\n", "\uD83D💖"]"#.as_bytes();
+    let err = Parser::from_reader(j).nth(1).unwrap().unwrap_err();
+    assert_eq!(err.to_string(), "lone surrogate: \\uD800");
+    assert_eq!(Parser::from_reader(j)
+                   .surrogate_policy(SurrogatePolicy::Replace)
+                   .collect::<Result<Vec<_>>>()
+                   .unwrap(),
+               vec![Event::Start(Block::Array),
+                    Event::String("\u{FFFD}".into()),
+                    Event::String("\u{FFFD}x".into()),
+                    Event::String("a\u{FFFD}b".into()),
+                    Event::String("\u{FFFD}\n".into()),
+                    Event::String("\u{FFFD}💖".into()),
+                    Event::End(Block::Array)]);
+    assert_eq!(Parser::from_reader(r#""\uD800""#.as_bytes())
+                   .surrogate_policy(SurrogatePolicy::Wtf8)
+                   .string_policy(StringPolicy::Raw)
+                   .next()
+                   .unwrap()
+                   .unwrap(),
+               Event::Bytes(vec![0xED, 0xA0, 0x80]));
+}
+
+#[test]
+fn test_next_ref() {
+    let j = r#"{"a": ["x\ty", 1.5, null]}"#.as_bytes();
+    let mut p = Parser::from_reader(j);
+    let mut events = Vec::new();
+    while let Some(ev) = p.next_ref().unwrap() {
+        events.push(ev.into_owned());
+    }
+    assert_eq!(events, Parser::from_reader(j).collect::<Result<Vec<_>>>().unwrap());
+
+    let mut p = Parser::from_reader(&b"\"a\xffb\""[..]).string_policy(StringPolicy::Lossy);
+    assert_eq!(p.next_ref().unwrap(), Some(EventRef::String("a\u{FFFD}b")));
+    let mut p = Parser::from_reader(&b"\"a\xffb\""[..]);
+    assert!(p.next_ref().is_err());
+}
+
+#[test]
+fn test_intern_keys() {
+    let j = r#"[{"a": 1, "b": 2}, {"a": 3, "c": 4}, {"b": 5, "c": 6}]"#.as_bytes();
+    let events = Parser::from_reader(j).intern_keys(2).collect::<Result<Vec<_>>>().unwrap();
+    let keys: Vec<&Event> = events.iter().filter(|e| e.key().is_some()).collect();
+    assert_eq!(keys.iter().map(|e| e.key().unwrap()).collect::<Vec<_>>(),
+               vec!["a", "b", "a", "c", "b", "c"]);
+    match (keys[0], keys[2]) {
+        (Event::InternedKey(x), Event::InternedKey(y)) => {
+            assert!(std::sync::Arc::ptr_eq(x, y))
+        }
+        _ => panic!("keys not interned"),
+    }
+    assert_eq!(keys[3], &Event::Key("c".into()));
+    assert_eq!(keys[5], &Event::Key("c".into()));
+}
+
+#[test]
+fn test_string_chunks() {
+    let j = r#"{"long key": "abcdéfgh", "s": "ab", "e": "abcd", "f": "abcde", "g": "abcé"}"#;
+    let events = Parser::from_reader(j.as_bytes())
+        .string_chunk_size(4)
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(events,
+               vec![Event::Start(Block::Object),
+                    Event::Key("long key".into()),
+                    Event::StringChunk("abcd".into()),
+                    Event::StringChunk("éfg".into()),
+                    Event::StringChunk("h".into()),
+                    Event::StringEnd,
+                    Event::Key("s".into()),
+                    Event::String("ab".into()),
+                    Event::Key("e".into()),
+                    Event::String("abcd".into()),
+                    Event::Key("f".into()),
+                    Event::StringChunk("abcd".into()),
+                    Event::StringChunk("e".into()),
+                    Event::StringEnd,
+                    Event::Key("g".into()),
+                    Event::StringChunk("abc".into()),
+                    Event::StringChunk("é".into()),
+                    Event::StringEnd,
+                    Event::End(Block::Object)]);
+
+    let mut tracker = path::PathTracker::new();
+    let mut p = Parser::from_reader(r#"["abcdefghij", 1]"#.as_bytes()).string_chunk_size(4);
+    loop {
+        let ev = p.next().unwrap().unwrap();
+        tracker.event(&ev);
+        if ev == Event::Number(N::Uint(1)) {
+            break;
+        }
+    }
+    assert_eq!(tracker.pointer(), "/1");
+}
+
+#[test]
+fn test_read_string_to() {
+    let long = "x\u{263A}".repeat(10_000);
+    let j = format!(r#"["{}", "a\nb", 1]"#, long);
+    let mut p = Parser::from_reader(j.as_bytes());
+    assert_eq!(p.next().unwrap().unwrap(), Event::Start(Block::Array));
+    let mut out = Vec::new();
+    assert_eq!(p.read_string_to(&mut out).unwrap(), long.len() as u64);
+    assert_eq!(out, long.as_bytes());
+    out.clear();
+    p.read_string_to(&mut out).unwrap();
+    assert_eq!(out, b"a\nb");
+    assert!(p.read_string_to(&mut out).is_err());
+}
+
+#[test]
+fn test_copy_value_to() {
+    let j = r#"[{"a": ["bcdefg", {}]}, "hijklm", 2, {"n"#;
+    let mut p = Parser::from_reader(j.as_bytes()).string_chunk_size(4);
+    assert_eq!(p.next().unwrap().unwrap(), Event::Start(Block::Array));
+    let mut w = writer::JsonWriter::new(Vec::new());
+    for _ in 0..3 {
+        let ev = p.next().unwrap().unwrap();
+        p.copy_value_to(ev, &mut w).unwrap();
+    }
+    assert_eq!(w.into_inner(), b"{\"a\":[\"bcdefg\",{}]}\n\"hijklm\"\n2");
+    let ev = p.next().unwrap().unwrap();
+    assert!(p.read_value_with(ev, |_| Ok(())).is_err());
+
+    let mut p = Parser::from_reader(&b"[1]"[..]);
+    let _ = p.next();
+    let _ = p.next();
+    let end = p.next().unwrap().unwrap();
+    assert!(p.read_value_with(end, |_| Ok(())).is_err());
+}
+
+#[test]
+fn test_read_base64_to() {
+    let data: Vec<u8> = (0..30_000u32).map(|i| (i * 7 % 256) as u8).collect();
+    let std = "+/".to_string();
+    let url = "-_".to_string();
+    for &(alphabet, padded) in &[(&std, true), (&url, false)] {
+        let text = encode(&data, alphabet, padded);
+        let j = format!(r#"["{}", "!"]"#, text);
+        let mut p = Parser::from_reader(j.as_bytes()).string_chunk_size(1000);
+        p.next().unwrap().unwrap();
+        let mut out = Vec::new();
+        assert_eq!(p.read_base64_to(&mut out).unwrap(), data.len() as u64);
+        assert_eq!(out, data);
+        assert!(p.read_base64_to(&mut out).is_err());
+    }
+
+    assert_eq!(base64::decode("aGk=").unwrap(), b"hi");
+    assert_eq!(base64::decode("aGk").unwrap(), b"hi");
+    assert_eq!(base64::decode("aGVsbG8_").unwrap(), b"hello?");
+    assert!(base64::decode("aGk==").is_err());
+    assert!(base64::decode("a").is_err());
+    assert!(base64::decode("aG=k").is_err());
+}
+
+fn encode(data: &[u8], alphabet: &str, padded: bool) -> String {
+    let table = format!("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789{}",
+                        alphabet);
+    let table = table.as_bytes();
+    let mut s = String::new();
+    for group in data.chunks(3) {
+        let n = group.iter().enumerate().fold(0u32, |n, (i, &b)| n | ((b as u32) << (16 - 8 * i)));
+        for i in 0..group.len() + 1 {
+            s.push(table[((n >> (18 - 6 * i)) & 63) as usize] as char);
+        }
+        if padded {
+            for _ in group.len()..3 {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+#[test]
+fn test_peek() {
+    let j = r#"{"a": [1, "x", true, null], "b": {}}"#.as_bytes();
+    let mut p = Parser::from_reader(j);
+    let mut events = Vec::new();
+    let mut kinds = Vec::new();
+    while let Some(kind) = p.peek_kind().unwrap() {
+        kinds.push(kind);
+        let peeked = p.peek().unwrap().map(|ev| ev.to_ref().into_owned());
+        let ev = p.next().unwrap().unwrap();
+        assert_eq!(peeked.as_ref(), Some(&ev));
+        assert_eq!(ev.kind(), kind);
+        events.push(ev);
+    }
+    assert_eq!(events, Parser::from_reader(j).collect::<Result<Vec<_>>>().unwrap());
+    assert_eq!(kinds,
+               vec![Kind::Object, Kind::Key, Kind::Array, Kind::Number, Kind::String, Kind::Bool,
+                    Kind::Null, Kind::End, Kind::Key, Kind::Object, Kind::End, Kind::End]);
+
+    let mut p = Parser::from_reader(r#"["abcdefgh"]"#.as_bytes()).string_chunk_size(4);
+    p.next().unwrap().unwrap();
+    assert_eq!(p.peek_kind().unwrap(), Some(Kind::String));
+    let mut out = Vec::new();
+    p.read_string_to(&mut out).unwrap();
+    assert_eq!(out, b"abcdefgh");
+    assert_eq!(p.peek().unwrap(), Some(&Event::End(Block::Array)));
+    assert_eq!(p.next_ref().unwrap(), Some(EventRef::End(Block::Array)));
+    assert!(p.peek().unwrap().is_none());
+
+    let mut p = Parser::from_reader("[1 x]".as_bytes());
+    p.next().unwrap().unwrap();
+    p.next().unwrap().unwrap();
+    assert!(p.peek_kind().is_err());
+}
+
+#[test]
+fn test_duplicate_keys() {
+    let j = r#"{"a": 1, "b": {"a": 2, "c": 3}, "a": 4}"#;
+    let keys = |p: Parser<&[u8]>| -> Result<Vec<String>> {
+        let events = p.collect::<Result<Vec<_>>>()?;
+        Ok(events.iter().filter_map(|ev| ev.key().map(|k| k.to_string())).collect())
+    };
+    assert_eq!(keys(Parser::from_reader(j.as_bytes())).unwrap(), ["a", "b", "a", "c", "a"]);
+
+    let p = Parser::from_reader(j.as_bytes()).duplicate_keys(DuplicateKeys::Error);
+    match *keys(p).unwrap_err().kind() {
+        ErrorKind::DuplicateKey(ref key, at) => {
+            assert_eq!(key, "a");
+            assert_eq!(at, Position { offset: 32, line: 1 });
+        }
+        ref e => panic!("unexpected error {:?}", e),
+    }
+    // Keys are only compared within the same object.
+    let p = Parser::from_reader(&br#"[{"a": 1}, {"a": {"a": 2}}]"#[..])
+        .duplicate_keys(DuplicateKeys::Error);
+    assert!(keys(p).is_ok());
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = seen.clone();
+    let p = Parser::from_reader(j.as_bytes())
+        .on_duplicate_key(move |key, at| sink.lock().unwrap().push((key.to_string(), at.offset)));
+    assert_eq!(keys(p).unwrap(), ["a", "b", "a", "c", "a"]);
+    assert_eq!(*seen.lock().unwrap(), [("a".to_string(), 32)]);
+
+    // The policy keeps its keys outside of `Container`, which stays `Copy`.
+    fn copy<T: Copy>() {}
+    copy::<Container>();
+}
+
+#[test]
+fn test_warnings() {
+    let j = "\u{feff}{\"a\": [1, 2,], \"b\": 0.1, \"c\": 1.00000000000000000001,\n \
+             \"d\": 123456789012345678901, \"e\": 1e400, \"a\": {},}";
+    let mut p = Parser::from_reader(j.as_bytes())
+        .collect_warnings(true)
+        .duplicate_keys(DuplicateKeys::Warn);
+    while p.next_ref().unwrap().is_some() {}
+    let warnings: Vec<_> = p.warnings().iter().map(|w| (w.kind.clone(), w.position)).collect();
+    let at = |offset, line| Position { offset, line };
+    assert_eq!(warnings,
+               [(WarningKind::BomSkipped, at(0, 1)),
+                (WarningKind::TrailingComma, at(15, 1)),
+                (WarningKind::PrecisionLoss("1.00000000000000000001".into()), at(33, 1)),
+                (WarningKind::PrecisionLoss("123456789012345678901".into()), at(63, 2)),
+                (WarningKind::PrecisionLoss("1e400".into()), at(91, 2)),
+                (WarningKind::DuplicateKey("a".into()), at(98, 2)),
+                (WarningKind::TrailingComma, at(106, 2))]);
+    assert_eq!(p.take_warnings().len(), 7);
+    assert!(p.warnings().is_empty());
+    assert_eq!(format!("{}", warnings_of("[1,]")[0]), "trailing comma at line 1, offset 3");
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = seen.clone();
+    let p = Parser::from_reader(&b"[\"a\xffb\", {\"k\xfe\": 1}]"[..])
+        .string_policy(StringPolicy::Lossy)
+        .on_warning(move |w| sink.lock().unwrap().push(w.clone()));
+    let events = p.collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(events[1], Event::String("a\u{fffd}b".into()));
+    let kinds: Vec<_> = seen.lock().unwrap().iter().map(|w| w.kind.clone()).collect();
+    assert_eq!(kinds, [WarningKind::LossyUtf8, WarningKind::LossyUtf8]);
+
+    // Nothing is reported for what is plainly valid.
+    assert!(warnings_of(r#"{"a": [1.5, 0.1, -2e-3, 1e22, 18446744073709551615], "b": {}}"#)
+        .is_empty());
+
+    // Input that is not JSON at all is an error rather than a warning.
+    for j in &["{\"a\"}", "{\"a\":}", "{\"a\": [1", "[{}", "", " \n"] {
+        let mut p = Parser::from_reader(j.as_bytes()).collect_warnings(true);
+        let r = p.by_ref().collect::<Result<Vec<_>>>();
+        assert!(r.is_err(), "{:?}", j);
+        assert!(p.warnings().is_empty(), "{:?}", j);
+    }
+}
+
+fn warnings_of(j: &str) -> Vec<Warning> {
+    let mut p = Parser::from_reader(j.as_bytes()).collect_warnings(true);
+    while p.next_ref().unwrap().is_some() {}
+    p.take_warnings()
+}
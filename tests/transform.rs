@@ -0,0 +1,81 @@
+extern crate jsonpull;
+
+use jsonpull::*;
+use jsonpull::transform::Transform;
+use jsonpull::writer::JsonWriter;
+
+const DOC: &str = r#"{"id": 1, "items": [{"id": "a", "tags": ["x", "y"], "_meta": {"k": 1}},
+                                       {"id": "b", "tags": [], "price": 2.5}],
+                      "owner": {"name": "n", "address": {"city": "c"}}}"#;
+
+fn write<I: Iterator<Item = Result<Event>>>(events: I) -> String {
+    let mut w = JsonWriter::new(Vec::new());
+    w.write_events(events).unwrap();
+    String::from_utf8(w.into_inner()).unwrap()
+}
+
+fn parse(json: &str) -> Parser<&[u8]> {
+    Parser::from_reader(json.as_bytes())
+}
+
+#[test]
+fn test_writer() {
+    assert_eq!(write(parse(DOC)),
+               r#"{"id":1,"items":[{"id":"a","tags":["x","y"],"_meta":{"k":1}},{"id":"b","tags":[],"price":2.5}],"owner":{"name":"n","address":{"city":"c"}}}"#);
+    let mut w = JsonWriter::new(Vec::new()).indent(2);
+    w.write_events(parse(r#"{"a": [1, {}], "b": "q\"\u0001"}"#)).unwrap();
+    assert_eq!(String::from_utf8(w.into_inner()).unwrap(),
+               "{\n  \"a\": [\n    1,\n    {}\n  ],\n  \"b\": \"q\\\"\\u0001\"\n}");
+    assert_eq!(write(parse("1 [] \"s\"").multiple_values(true)), "1\n[]\n\"s\"");
+    assert_eq!(write(parse(r#"["abcdefgh", 1.0]"#).string_chunk_size(4)),
+               r#"["abcdefgh",1.0]"#);
+}
+
+#[test]
+fn test_filter_paths() {
+    assert_eq!(write(parse(DOC).filter_paths(&["/items/*/id", "/owner/address"]).unwrap()),
+               r#"{"items":[{"id":"a"},{"id":"b"}],"owner":{"address":{"city":"c"}}}"#);
+    assert_eq!(write(parse(DOC).filter_paths(&["/items/1/tags", "/nothing"]).unwrap()),
+               r#"{"items":[{"tags":[]}]}"#);
+    assert_eq!(write(parse(DOC).filter_paths(&["/missing"]).unwrap()), "");
+    assert!(parse(DOC).filter_paths(&["no-slash"]).is_err());
+}
+
+#[test]
+fn test_rename_and_drop_keys() {
+    let events = parse(DOC)
+        .drop_keys(|k| k.starts_with('_') || k == "owner")
+        .rename_keys(|k| if k == "id" { Some("ID".into()) } else { None });
+    assert_eq!(write(events),
+               r#"{"ID":1,"items":[{"ID":"a","tags":["x","y"]},{"ID":"b","tags":[],"price":2.5}]}"#);
+}
+
+#[test]
+fn test_map_values() {
+    let events = parse(r#"{"a": [1, "s", null], "b": true}"#).map_values(|ev| {
+        match ev {
+            Event::Number(N::Uint(n)) => Event::Number(N::Uint(n * 10)),
+            Event::String(s) => Event::String(s.to_uppercase()),
+            ev => ev,
+        }
+    });
+    assert_eq!(write(events), r#"{"a":[10,"S",null],"b":true}"#);
+    let mut events = parse("[1]").map_values(|_| Event::Start(Block::Array));
+    events.next();
+    assert!(events.next().unwrap().is_err());
+}
+
+#[test]
+fn test_flatten() {
+    assert_eq!(write(parse(DOC).flatten_keys(".")),
+               r#"{"id":1,"items.0.id":"a","items.0.tags.0":"x","items.0.tags.1":"y","items.0._meta.k":1,"items.1.id":"b","items.1.tags":[],"items.1.price":2.5,"owner.name":"n","owner.address.city":"c"}"#);
+    assert_eq!(write(parse(r#"[["abcdefgh"], {}] 5"#).multiple_values(true).string_chunk_size(4).flatten_keys("/")),
+               "{\"0/0\":\"abcdefgh\",\"1\":{}}\n5");
+}
+
+#[test]
+fn test_take_depth() {
+    assert_eq!(write(parse(DOC).take_depth(2)),
+               r#"{"id":1,"items":[{},{}],"owner":{"name":"n","address":{}}}"#);
+    assert_eq!(write(parse(DOC).take_depth(0)), "{}");
+}
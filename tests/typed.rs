@@ -0,0 +1,84 @@
+extern crate jsonpull;
+
+use jsonpull::*;
+
+#[derive(Debug, PartialEq)]
+struct User {
+    id: u64,
+    name: String,
+    email: Option<String>,
+    score: f64,
+    active: bool,
+}
+
+fn read_user<B: std::io::BufRead>(p: &mut Parser<B>) -> Result<User> {
+    p.expect_object_start()?;
+    let mut user = User {
+        id: 0,
+        name: String::new(),
+        email: None,
+        score: 0.0,
+        active: false,
+    };
+    while let Some(key) = p.next_key()? {
+        match key.as_str() {
+            "id" => user.id = p.read_u64()?,
+            "name" => user.name = p.read_string()?,
+            "email" => user.email = p.read_opt_string()?,
+            "score" => user.score = p.read_f64()?,
+            "active" => user.active = p.read_bool()?,
+            _ => p.skip_value()?,
+        }
+    }
+    Ok(user)
+}
+
+#[test]
+fn test_typed_accessors() {
+    let j = r#"{"id": 7, "extra": {"a": [1, {"b": "ccccccccc"}]}, "name": "a long name",
+                "email": null, "score": -2, "active": true}"#;
+    let mut p = Parser::from_reader(j.as_bytes()).string_chunk_size(4);
+    assert_eq!(read_user(&mut p).unwrap(),
+               User {
+                   id: 7,
+                   name: "a long name".into(),
+                   email: None,
+                   score: -2.0,
+                   active: true,
+               });
+    assert!(p.next().is_none());
+
+    let mut p = Parser::from_reader("[-1, 2, 3.5]".as_bytes());
+    p.expect_array_start().unwrap();
+    assert_eq!(p.read_i64().unwrap(), -1);
+    assert_eq!(p.read_i64().unwrap(), 2);
+    p.skip_value().unwrap();
+    p.expect_array_end().unwrap();
+}
+
+#[test]
+fn test_typed_errors() {
+    let j = "{\"id\": 1,\n \"name\": 5}";
+    let mut p = Parser::from_reader(j.as_bytes());
+    let err = read_user(&mut p).unwrap_err();
+    match *err.kind() {
+        ErrorKind::Unexpected(expected, ref found, at) => {
+            assert_eq!(expected, "string");
+            assert_eq!(found, "number 5");
+            assert_eq!(at, Position { offset: 19, line: 2 });
+        }
+        ref e => panic!("unexpected error {:?}", e),
+    }
+    assert_eq!(err.to_string(), "expected string, found number 5 at line 2, offset 19");
+
+    let mut p = Parser::from_reader("[-1]".as_bytes());
+    p.expect_array_start().unwrap();
+    assert!(p.read_u64().is_err());
+    let mut p = Parser::from_reader("[]".as_bytes());
+    p.expect_array_start().unwrap();
+    assert!(p.skip_value().is_err());
+    let mut p = Parser::from_reader("{\"a\": 1}".as_bytes());
+    p.expect_object_start().unwrap();
+    assert!(p.skip_value().is_err());
+    assert!(Parser::from_reader("".as_bytes()).read_bool().is_err());
+}
@@ -0,0 +1,145 @@
+extern crate jsonpull;
+
+use jsonpull::validate::{Schema, Violation};
+use jsonpull::value::Value;
+use jsonpull::Parser;
+
+fn violations(schema: &str, json: &str) -> Vec<(String, &'static str)> {
+    let schema = Schema::parse(schema).unwrap();
+    let mut p = Parser::from_reader(json.as_bytes())
+        .multiple_values(true)
+        .string_chunk_size(4);
+    schema.validate(&mut p)
+        .unwrap()
+        .into_iter()
+        .map(|v| (v.pointer, v.keyword))
+        .collect()
+}
+
+fn at(pointer: &str, keyword: &'static str) -> (String, &'static str) {
+    (pointer.to_string(), keyword)
+}
+
+const RECORD: &str = r##"{
+    "type": "object",
+    "properties": {
+        "id": {"type": "integer", "minimum": 1, "exclusiveMaximum": 100},
+        "name": {"type": "string", "minLength": 2, "maxLength": 8},
+        "ratio": {"type": "number", "exclusiveMinimum": 0, "maximum": 1.5},
+        "tags": {"type": "array", "items": {"enum": ["a", "b", 3]}, "maxItems": 3},
+        "kind": {"const": {"x": [1, null]}},
+        "next": {"$ref": "#"},
+        "parts": {"type": "array", "items": {"$ref": "#/$defs/part"}, "minItems": 1}
+    },
+    "required": ["id", "name"],
+    "additionalProperties": false,
+    "$defs": {
+        "part": {"type": ["string", "null"], "enum": ["p", null]}
+    }
+}"##;
+
+#[test]
+fn test_validate_valid() {
+    let json = r#"{"id": 1, "name": "ab", "ratio": 1.5, "tags": ["a", 3.0],
+                   "kind": {"x": [1.0, null]}, "parts": ["p", null],
+                   "next": {"id": 99.0, "name": "long one"}}"#;
+    assert_eq!(violations(RECORD, json), vec![]);
+}
+
+#[test]
+fn test_validate_invalid() {
+    let json = r#"{"id": 0.5, "name": "a", "ratio": 0, "tags": ["c", "b", 3, 3],
+                   "kind": {"x": [1]}, "extra": {"deep": 1}, "parts": [],
+                   "next": {"id": 100, "next": {"name": "abcdefghi", "parts": ["q"]}}}"#;
+    assert_eq!(violations(RECORD, json),
+               vec![at("/id", "type"),
+                    at("/id", "minimum"),
+                    at("/name", "minLength"),
+                    at("/ratio", "exclusiveMinimum"),
+                    at("/tags/0", "enum"),
+                    at("/tags", "maxItems"),
+                    at("/kind", "const"),
+                    at("/extra", "additionalProperties"),
+                    at("/parts", "minItems"),
+                    at("/next/id", "exclusiveMaximum"),
+                    at("/next/next/name", "maxLength"),
+                    at("/next/next/parts/0", "enum"),
+                    at("/next/next", "required"),
+                    at("/next", "required")]);
+    assert_eq!(violations(RECORD, "[]"), vec![at("", "type")]);
+    assert_eq!(violations(r#"{"items": false}"#, "[1, [2]] [] 3"),
+               vec![at("/0", "false"), at("/1", "false")]);
+}
+
+#[test]
+fn test_validate_positions() {
+    let schema = Schema::parse(r#"{"items": {"type": "string"}}"#).unwrap();
+    let mut p = Parser::from_reader(&b"[\"a\",\n 2, {}]"[..]);
+    let violations = schema.validate(&mut p).unwrap();
+    assert_eq!(violations.len(), 2);
+    assert_eq!((violations[0].position.line, violations[0].position.offset), (2, 7));
+    assert_eq!((violations[1].position.line, violations[1].position.offset), (2, 10));
+    assert_eq!(violations[0].to_string(),
+               "expected string, found integer at /1 (line 2, offset 7)");
+
+    // Stops at the first violation without reading further.
+    let mut p = Parser::from_reader(&b"[1, 2, oops"[..]);
+    let mut first: Option<Violation> = None;
+    let valid = schema.validate_with(&mut p, |v| {
+            first = Some(v);
+            false
+        })
+        .unwrap();
+    assert!(!valid);
+    assert_eq!(first.unwrap().pointer, "/0");
+}
+
+#[test]
+fn test_validate_truncated() {
+    let schema = Schema::parse(r#"{"required": ["b"]}"#).unwrap();
+    for json in &["{\"a\": [1", "{\"a\": 1, \"b\": \"abcdef", "{\"a\": 1"] {
+        let mut p = Parser::from_reader(json.as_bytes()).string_chunk_size(4);
+        assert!(schema.validate(&mut p).is_err(), "{}", json);
+    }
+}
+
+#[test]
+fn test_schema_errors() {
+    assert!(Schema::parse(r#"{"type": "float"}"#).is_err());
+    assert!(Schema::parse(r#"{"$ref": "other.json#/x"}"#).is_err());
+    assert!(Schema::parse(r##"{"$ref": "#/$defs/missing"}"##).is_err());
+    assert!(Schema::parse(r#"{"minLength": -1}"#).is_err());
+    assert!(Schema::parse("[]").is_err());
+    // Recursive references are fine.
+    let recursive = r##"{"$defs": {"a": {"items": {"$ref": "#/$defs/a"}}}, "$ref": "#/$defs/a"}"##;
+    assert!(Schema::parse(recursive).is_ok());
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_validate_pattern() {
+    let schema = r#"{"items": {"pattern": "^[a-z]+-[0-9]+$"}}"#;
+    assert_eq!(violations(schema, r#"["ab-12", "abc-123456", "AB-1", "x-1 "]"#),
+               vec![at("/2", "pattern"), at("/3", "pattern")]);
+}
+
+#[cfg(not(feature = "regex"))]
+#[test]
+fn test_validate_pattern() {
+    assert!(Schema::parse(r#"{"pattern": "a"}"#).is_err());
+}
+
+#[test]
+fn test_value() {
+    let v = Value::parse(r#"{"a": [1, {"b~/": "c"}], "n": null, "a": 2}"#).unwrap();
+    assert_eq!(v.get("a").unwrap().as_u64(), Some(2));
+    assert_eq!(v.pointer("/n"), Some(&Value::Null));
+    assert_eq!(v.pointer(""), Some(&v));
+    assert_eq!(v.pointer("/a/0"), None);
+    assert_eq!(Value::parse(r#"{"y": 1, "x": [2.0]}"#).unwrap(),
+               Value::parse(r#"{"x": [2], "y": 1.0}"#).unwrap());
+    assert!(Value::parse(r#"{"x": 1}"#).unwrap() != Value::parse(r#"{"x": 1, "y": 2}"#).unwrap());
+    let nested = Value::parse(r#"[{"b~/": "c"}]"#).unwrap();
+    assert_eq!(nested.pointer("/0/b~0~1").and_then(|v| v.as_str()), Some("c"));
+    assert!(Value::parse("[1").is_err());
+}